@@ -0,0 +1,44 @@
+//! The blocking flavour of the HTTP clients.
+
+use std::io::{self, Read};
+use std::time::Duration;
+
+pub use crate::common::blocking::{BaseHttpClient, StreamingResponse};
+#[cfg(feature = "client-ureq")]
+pub use crate::ureq::{UreqClient as HttpClient, UreqClientError as ClientError};
+// ureq stays the default blocking client when both backends are enabled
+#[cfg(all(feature = "client-reqwest-blocking", not(feature = "client-ureq")))]
+pub use crate::reqwest::{ReqwestBlockingClient as HttpClient, ReqwestClientError as ClientError};
+
+/// The flavour specific items used by the code compiled with `flavoured!`.
+pub(crate) mod prelude {
+  #[cfg(test)]
+  pub(crate) use core::prelude::v1::test as async_test;
+  pub(crate) use maybe_async::must_be_sync as maybe_async;
+
+  #[cfg(feature = "tracing")]
+  pub(crate) use super::in_span;
+  pub(crate) use super::sleep;
+  pub(crate) use super::{buffered_body, BaseHttpClient, ResponseBody, StreamingResponse};
+}
+
+/// The body of a [`StreamingResponse`], read as it is received.
+pub type ResponseBody = Box<dyn Read + Send>;
+
+/// A body reading the whole buffered content.
+pub(crate) fn buffered_body(body: Vec<u8>) -> ResponseBody {
+  Box::new(io::Cursor::new(body))
+}
+
+pub(crate) fn sleep(duration: Duration) {
+  std::thread::sleep(duration)
+}
+
+/// Runs the request made by `send` inside the given span.
+#[cfg(feature = "tracing")]
+pub(crate) fn in_span<F, T>(span: &tracing::Span, send: F) -> T
+where
+  F: FnOnce() -> T,
+{
+  span.in_scope(send)
+}
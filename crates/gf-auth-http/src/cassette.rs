@@ -0,0 +1,592 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+
+use crate::common::{Body, Headers, HttpError, HttpRequest, HttpResponse, Method, Multipart};
+use crate::logging::{RequestLogger, REDACTED};
+
+#[derive(thiserror::Error, Debug)]
+pub enum CassetteError {
+  #[error("no recorded interaction matched request {method} {url}")]
+  NoMatch { method: Method, url: String },
+  #[error("I/O: {0}")]
+  IO(#[from] io::Error),
+  #[error("JSON: {0}")]
+  Json(#[from] serde_json::Error),
+}
+
+/// The payload of a recorded request.
+///
+/// Requests without a body record their query parameters as the payload, the query of
+/// the other requests is kept in the recorded url.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", content = "value", rename_all = "lowercase")]
+pub enum CassetteBody {
+  None,
+  Query(BTreeMap<String, String>),
+  Json(Value),
+  Form(BTreeMap<String, String>),
+  Bytes(Vec<u8>),
+  Multipart(Multipart),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CassetteRequest {
+  pub method: String,
+  pub url: String,
+  pub headers: Headers,
+  pub body: CassetteBody,
+}
+
+/// The body of a recorded response, kept as text unless it isn't valid UTF-8.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum CassetteResponseBody {
+  Text(String),
+  Bytes(Vec<u8>),
+}
+
+/// A recorded response, non-`2xx` responses are replayed as [`HttpError::Status`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CassetteResponse {
+  pub status: u16,
+  pub headers: Headers,
+  pub body: CassetteResponseBody,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Interaction {
+  pub request: CassetteRequest,
+  pub response: CassetteResponse,
+}
+
+/// A list of recorded request/response pairs, stored as a JSON file.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Cassette {
+  pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+  pub fn load(path: impl AsRef<Path>) -> Result<Self, CassetteError> {
+    let contents = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&contents)?)
+  }
+
+  pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CassetteError> {
+    fs::write(path, serde_json::to_string_pretty(self)?)?;
+    Ok(())
+  }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+  mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Redacts secrets from the recorded requests and responses with the rules of
+/// a [`RequestLogger`]. Session tokens in response bodies are redacted as well.
+fn default_redaction() -> RequestLogger {
+  RequestLogger::default().with_redacted_field("token")
+}
+
+fn redact_body(redaction: &RequestLogger, body: &str) -> String {
+  match serde_json::from_str::<Value>(body) {
+    Ok(value) => redaction.redact_json(&value).to_string(),
+    Err(_) => body.to_owned(),
+  }
+}
+
+fn cassette_request(redaction: &RequestLogger, request: &HttpRequest) -> CassetteRequest {
+  let (url, body) = match &request.body {
+    Body::Empty if request.query.is_empty() => (request.url.clone(), CassetteBody::None),
+    Body::Empty => (
+      request.url.clone(),
+      CassetteBody::Query(redaction.redact_pairs(&request.query)),
+    ),
+    Body::Json(value) => (
+      request.full_url(),
+      CassetteBody::Json(redaction.redact_json(value)),
+    ),
+    Body::Form(pairs) => (
+      request.full_url(),
+      CassetteBody::Form(redaction.redact_pairs(pairs)),
+    ),
+    Body::Bytes(bytes) => (request.full_url(), CassetteBody::Bytes(bytes.clone())),
+    Body::Multipart(multipart) => {
+      let mut multipart = multipart.clone();
+      for part in &mut multipart.parts {
+        if part.filename.is_none() && redaction.is_redacted_field(&part.name) {
+          part.data = REDACTED.as_bytes().to_vec();
+        }
+      }
+      (request.full_url(), CassetteBody::Multipart(multipart))
+    }
+  };
+
+  CassetteRequest {
+    method: request.method.as_str().to_owned(),
+    url: redaction.redact_url(&url),
+    headers: redaction.redact_headers(&request.headers),
+    body,
+  }
+}
+
+/// A `BaseHttpClient` wrapper which records every request made through it, along with
+/// the response, into a [`Cassette`].
+///
+/// Secrets are redacted using the rules of a [`RequestLogger`], by default the same ones
+/// used for logging plus the `token` field. Requests which failed without a response
+/// (e.g. connection errors) are not recorded.
+#[derive(Debug, Clone)]
+pub struct RecordingClient<C> {
+  inner: C,
+  redaction: RequestLogger,
+  cassette: Arc<Mutex<Cassette>>,
+  path: Option<PathBuf>,
+}
+
+impl<C: Default> Default for RecordingClient<C> {
+  fn default() -> Self {
+    Self::new(C::default())
+  }
+}
+
+impl<C> RecordingClient<C> {
+  pub fn new(inner: C) -> Self {
+    RecordingClient {
+      inner,
+      redaction: default_redaction(),
+      cassette: Default::default(),
+      path: None,
+    }
+  }
+
+  /// Writes the cassette to the given file after every recorded interaction.
+  pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+    self.path = Some(path.into());
+    self
+  }
+
+  pub fn with_redaction(mut self, redaction: RequestLogger) -> Self {
+    self.redaction = redaction;
+    self
+  }
+
+  pub fn inner(&self) -> &C {
+    &self.inner
+  }
+
+  /// A snapshot of the interactions recorded so far.
+  pub fn cassette(&self) -> Cassette {
+    lock(&self.cassette).clone()
+  }
+
+  fn record<E: Error>(
+    &self,
+    request: CassetteRequest,
+    result: &Result<HttpResponse, HttpError<E>>,
+  ) {
+    let (status, headers, body) = match result {
+      Ok(response) => (response.status(), response.headers(), response.bytes()),
+      Err(HttpError::Status {
+        status,
+        headers,
+        body,
+      }) => (*status, headers, body.as_slice()),
+      Err(_) => return,
+    };
+    let response = CassetteResponse {
+      status,
+      headers: self.redaction.redact_headers(headers),
+      body: match std::str::from_utf8(body) {
+        Ok(text) => CassetteResponseBody::Text(redact_body(&self.redaction, text)),
+        Err(_) => CassetteResponseBody::Bytes(body.to_vec()),
+      },
+    };
+
+    let mut cassette = lock(&self.cassette);
+    cassette
+      .interactions
+      .push(Interaction { request, response });
+
+    if let Some(path) = &self.path {
+      if let Err(err) = cassette.save(path) {
+        log::warn!("Failed to save cassette to {}: {err}", path.display());
+      }
+    }
+  }
+}
+
+/// Rules deciding which recorded interaction answers a request in a [`ReplayClient`].
+///
+/// The method and the URL (without the query string) always have to match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchRules {
+  /// Compare the query string and the query parameters.
+  pub query: bool,
+  /// Compare the bodies, otherwise only their kind has to match.
+  pub body: bool,
+  /// Headers which have to match, compared case-insensitively by name.
+  pub headers: Vec<String>,
+  /// Allow an interaction to be replayed more than once. Otherwise every interaction
+  /// is used once, in the order it was recorded.
+  pub repeat: bool,
+}
+
+impl Default for MatchRules {
+  fn default() -> Self {
+    MatchRules {
+      query: true,
+      body: true,
+      headers: vec![],
+      repeat: false,
+    }
+  }
+}
+
+impl MatchRules {
+  pub fn with_query(mut self, query: bool) -> Self {
+    self.query = query;
+    self
+  }
+
+  pub fn with_body(mut self, body: bool) -> Self {
+    self.body = body;
+    self
+  }
+
+  pub fn with_header(mut self, name: impl Into<String>) -> Self {
+    self.headers.push(name.into());
+    self
+  }
+
+  pub fn with_repeat(mut self, repeat: bool) -> Self {
+    self.repeat = repeat;
+    self
+  }
+
+  fn matches(&self, recorded: &CassetteRequest, request: &CassetteRequest) -> bool {
+    recorded.method == request.method
+      && self.url_matches(&recorded.url, &request.url)
+      && self.body_matches(&recorded.body, &request.body)
+      && self.headers.iter().all(|name| {
+        recorded
+          .headers
+          .get_all(name)
+          .eq(request.headers.get_all(name))
+      })
+  }
+
+  fn url_matches(&self, recorded: &str, url: &str) -> bool {
+    if self.query {
+      return recorded == url;
+    }
+
+    match (Url::parse(recorded), Url::parse(url)) {
+      (Ok(mut recorded), Ok(mut url)) => {
+        recorded.set_query(None);
+        url.set_query(None);
+        recorded == url
+      }
+      _ => recorded == url,
+    }
+  }
+
+  fn body_matches(&self, recorded: &CassetteBody, body: &CassetteBody) -> bool {
+    use CassetteBody::{None, Query};
+
+    match (recorded, body) {
+      (None | Query(_), None | Query(_)) => !self.query || recorded == body,
+      _ if !self.body => mem::discriminant(recorded) == mem::discriminant(body),
+      _ => recorded == body,
+    }
+  }
+}
+
+/// A `BaseHttpClient` which answers requests with the interactions of a [`Cassette`],
+/// without making any network calls.
+///
+/// Incoming requests are redacted with the same rules as the [`RecordingClient`] before
+/// they're compared with the recorded ones. Clones share the replay progress.
+#[derive(Debug, Clone)]
+pub struct ReplayClient {
+  cassette: Arc<Cassette>,
+  rules: MatchRules,
+  redaction: RequestLogger,
+  used: Arc<Mutex<Vec<bool>>>,
+}
+
+impl Default for ReplayClient {
+  fn default() -> Self {
+    Self::new(Cassette::default())
+  }
+}
+
+impl ReplayClient {
+  pub fn new(cassette: Cassette) -> Self {
+    let used = vec![false; cassette.interactions.len()];
+    ReplayClient {
+      cassette: Arc::new(cassette),
+      rules: MatchRules::default(),
+      redaction: default_redaction(),
+      used: Arc::new(Mutex::new(used)),
+    }
+  }
+
+  pub fn from_file(path: impl AsRef<Path>) -> Result<Self, CassetteError> {
+    Ok(Self::new(Cassette::load(path)?))
+  }
+
+  pub fn with_rules(mut self, rules: MatchRules) -> Self {
+    self.rules = rules;
+    self
+  }
+
+  /// Sets the redaction rules, which should be the ones the cassette was recorded with.
+  pub fn with_redaction(mut self, redaction: RequestLogger) -> Self {
+    self.redaction = redaction;
+    self
+  }
+
+  pub fn cassette(&self) -> &Cassette {
+    &self.cassette
+  }
+
+  /// Whether every recorded interaction has been replayed at least once.
+  pub fn is_finished(&self) -> bool {
+    lock(&self.used).iter().all(|used| *used)
+  }
+
+  fn replay(&self, request: &HttpRequest) -> Result<HttpResponse, HttpError<CassetteError>> {
+    let method = request.method;
+    let request = cassette_request(&self.redaction, request);
+
+    let mut used = lock(&self.used);
+    let matched = self
+      .cassette
+      .interactions
+      .iter()
+      .enumerate()
+      .find(|(index, interaction)| {
+        (self.rules.repeat || !used[*index]) && self.rules.matches(&interaction.request, &request)
+      });
+
+    let Some((index, interaction)) = matched else {
+      return Err(HttpError::Client(CassetteError::NoMatch {
+        method,
+        url: request.url,
+      }));
+    };
+    used[index] = true;
+
+    let response = &interaction.response;
+    let headers = response.headers.clone();
+
+    let body = match &response.body {
+      CassetteResponseBody::Text(text) => text.clone().into_bytes(),
+      CassetteResponseBody::Bytes(bytes) => bytes.clone(),
+    };
+
+    if (200..300).contains(&response.status) {
+      Ok(HttpResponse::new(response.status, headers, body))
+    } else {
+      Err(HttpError::from_status(response.status, headers, body))
+    }
+  }
+}
+
+flavoured! {
+  #[maybe_async]
+  async fn execute<C, F, Fut, E>(
+    client: &RecordingClient<C>,
+    request: CassetteRequest,
+    send: F,
+  ) -> Result<HttpResponse, HttpError<E>>
+  where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = Result<HttpResponse, HttpError<E>>>,
+    E: Error,
+  {
+    let result = send().await;
+    client.record(request, &result);
+    result
+  }
+
+  #[maybe_async]
+  impl<C, E> BaseHttpClient for RecordingClient<C>
+  where
+    C: BaseHttpClient<Error = HttpError<E>> + Sync,
+    E: Error + Send,
+  {
+    type Error = C::Error;
+
+    async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Self::Error> {
+      let recorded = cassette_request(&self.redaction, request);
+      execute(self, recorded, || self.inner.send(request)).await
+    }
+  }
+
+  #[maybe_async]
+  impl BaseHttpClient for ReplayClient {
+    type Error = HttpError<CassetteError>;
+
+    async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Self::Error> {
+      self.replay(request)
+    }
+  }
+
+  #[cfg(test)]
+  mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::cassette::{
+      Cassette, CassetteBody, CassetteResponseBody, MatchRules, RecordingClient, ReplayClient,
+    };
+    use crate::common::{Headers, HttpError, Query};
+    use crate::testing::ScriptedClient;
+
+    const SESSIONS: &str = "https://spark.gameforge.com/api/v1/auth/sessions";
+    const ACCOUNTS: &str = "https://spark.gameforge.com/api/v1/user/accounts";
+
+    fn credentials() -> serde_json::Value {
+      json!({ "email": "user@example.com", "password": "hunter2" })
+    }
+
+    #[maybe_async]
+    async fn record() -> Cassette {
+      let client = RecordingClient::new(ScriptedClient::new(&[Ok(201), Err(403)]));
+      let headers = Headers::from([("Authorization".to_string(), "Bearer secret".to_string())]);
+
+      let _ = client.post(SESSIONS, None, &credentials()).await;
+      let _ = client.get(ACCOUNTS, Some(&headers), &Query::new()).await;
+      client.cassette()
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn records_with_secrets_redacted() {
+      let cassette = record().await;
+      assert_eq!(cassette.interactions.len(), 2);
+
+      let login = &cassette.interactions[0];
+      assert_eq!(login.request.method, "POST");
+      assert_eq!(
+        login.request.body,
+        CassetteBody::Json(json!({ "email": "user@example.com", "password": "<redacted>" }))
+      );
+      assert_eq!(login.response.status, 201);
+
+      let accounts = &cassette.interactions[1];
+      assert_eq!(accounts.request.headers.get("Authorization"), Some("<redacted>"));
+      assert_eq!(accounts.response.status, 403);
+      assert_eq!(
+        accounts.response.body,
+        CassetteResponseBody::Text("error 403".into())
+      );
+
+      let path = std::env::temp_dir().join(format!("gf-auth-{}.json", uuid::Uuid::new_v4()));
+      cassette.save(&path).unwrap();
+      let loaded = Cassette::load(&path).unwrap();
+      std::fs::remove_file(&path).unwrap();
+      assert_eq!(loaded, cassette);
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn replays_recorded_interactions() {
+      let client = ReplayClient::new(record().await);
+
+      let login = client.post(SESSIONS, None, &credentials()).await.unwrap();
+      assert_eq!(login.status(), 201);
+
+      let accounts = client.get(ACCOUNTS, None, &Query::new()).await;
+      match accounts {
+        Err(HttpError::Status { status, body, .. }) => {
+          assert_eq!(status, 403);
+          assert_eq!(body, b"error 403");
+        }
+        _ => panic!("expected a status error"),
+      }
+      assert!(client.is_finished());
+
+      let again = client.post(SESSIONS, None, &credentials()).await;
+      assert!(matches!(again, Err(HttpError::Client(_))));
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn replays_binary_bodies() {
+      let mut cassette = record().await;
+      cassette.interactions[0].response.body = CassetteResponseBody::Bytes(vec![0x89, b'P', 0]);
+      let json = serde_json::to_value(&cassette).unwrap();
+      assert_eq!(json["interactions"][0]["response"]["body"], json!([0x89, 80, 0]));
+      assert_eq!(json["interactions"][1]["response"]["body"], json!("error 403"));
+
+      let client = ReplayClient::new(serde_json::from_value(json).unwrap());
+      let login = client.post(SESSIONS, None, &credentials()).await.unwrap();
+      assert_eq!(login.bytes(), [0x89, b'P', 0]);
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn applies_match_rules() {
+      let cassette = record().await;
+      let other = json!({ "email": "other@example.com", "password": "hunter2" });
+
+      let strict = ReplayClient::new(cassette.clone());
+      let result = strict.post(SESSIONS, None, &other).await;
+      assert!(result.is_err());
+
+      let lenient = ReplayClient::new(cassette)
+        .with_rules(MatchRules::default().with_body(false).with_repeat(true));
+      let first = lenient.post(SESSIONS, None, &other).await;
+      let second = lenient.post(SESSIONS, None, &other).await;
+      assert!(first.is_ok() && second.is_ok());
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn keeps_repeated_headers_in_order() {
+      let client = RecordingClient::new(ScriptedClient::default());
+      let mut headers = Headers::new();
+      headers.append("Accept", "text/html");
+      headers.append("Accept", "application/json");
+      let _ = client.get(ACCOUNTS, Some(&headers), &Query::new()).await;
+      let cassette = client.cassette();
+
+      let json = serde_json::to_value(&cassette).unwrap();
+      assert_eq!(
+        json["interactions"][0]["request"]["headers"],
+        json!([["Accept", "text/html"], ["Accept", "application/json"]])
+      );
+      let loaded: Cassette = serde_json::from_value(json).unwrap();
+      assert_eq!(loaded.interactions[0].request.headers, headers);
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn matches_queries_unless_disabled() {
+      let recorder = RecordingClient::new(ScriptedClient::default());
+      let mut query = Query::new();
+      query.insert("page", "2");
+      let _ = recorder.get(ACCOUNTS, None, &query).await;
+      let cassette = recorder.cassette();
+
+      let strict = ReplayClient::new(cassette.clone());
+      let result = strict.get(ACCOUNTS, None, &Query::new()).await;
+      assert!(result.is_err());
+
+      let lenient = ReplayClient::new(cassette).with_rules(MatchRules::default().with_query(false));
+      let result = lenient.get(ACCOUNTS, None, &Query::new()).await;
+      assert!(result.is_ok());
+    }
+  }
+}
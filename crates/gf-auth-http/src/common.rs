@@ -1,27 +1,175 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::error::Error;
+use std::time::Duration;
 use std::{fmt, io};
 
-use maybe_async::maybe_async;
+use serde::de::DeserializeOwned;
 use serde_json::Value;
+use url::{form_urlencoded, Url};
 
-pub type Headers = HashMap<String, String>;
 pub type Query<'a> = HashMap<&'a str, &'a str>;
 pub type Form<'a> = HashMap<&'a str, &'a str>;
 
+/// HTTP headers, kept in the order they were added.
+///
+/// Names are compared case-insensitively and keep the case they were added with, and
+/// a name can have several values, such as the `Set-Cookie` headers of a response.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(
+  feature = "cassette",
+  derive(serde::Serialize, serde::Deserialize),
+  serde(transparent)
+)]
+pub struct Headers {
+  entries: Vec<(String, String)>,
+}
+
+impl Headers {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Returns the first value of the header.
+  pub fn get(&self, name: &str) -> Option<&str> {
+    self
+      .entries
+      .iter()
+      .find(|(key, _)| key.eq_ignore_ascii_case(name))
+      .map(|(_, value)| value.as_str())
+  }
+
+  pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    self
+      .entries
+      .iter()
+      .filter(move |(key, _)| key.eq_ignore_ascii_case(name))
+      .map(|(_, value)| value.as_str())
+  }
+
+  pub fn contains_key(&self, name: &str) -> bool {
+    self.get(name).is_some()
+  }
+
+  /// Sets the value of the header, replacing all its previous values in place of the
+  /// first one.
+  pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>) {
+    let name = name.into();
+    let mut value = Some(value.into());
+    self.entries.retain_mut(|(key, current)| {
+      if !key.eq_ignore_ascii_case(&name) {
+        return true;
+      }
+      match value.take() {
+        Some(value) => {
+          key.clone_from(&name);
+          *current = value;
+          true
+        }
+        None => false,
+      }
+    });
+    if let Some(value) = value {
+      self.entries.push((name, value));
+    }
+  }
+
+  /// Adds a value to the header, keeping its previous values.
+  pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>) {
+    self.entries.push((name.into(), value.into()));
+  }
+
+  /// Removes all the values of the header, returning the first one.
+  pub fn remove(&mut self, name: &str) -> Option<String> {
+    let mut removed = None;
+    self.entries.retain_mut(|(key, value)| {
+      if !key.eq_ignore_ascii_case(name) {
+        return true;
+      }
+      if removed.is_none() {
+        removed = Some(std::mem::take(value));
+      }
+      false
+    });
+    removed
+  }
+
+  /// Iterates over the headers in order, once for every value.
+  pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+    self
+      .entries
+      .iter()
+      .map(|(name, value)| (name.as_str(), value.as_str()))
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for Headers {
+  fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+    let mut headers = Headers::new();
+    headers.extend(iter);
+    headers
+  }
+}
+
+impl<K: Into<String>, V: Into<String>> Extend<(K, V)> for Headers {
+  fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+    for (name, value) in iter {
+      self.append(name, value);
+    }
+  }
+}
+
+impl<K: Into<String>, V: Into<String>, const N: usize> From<[(K, V); N]> for Headers {
+  fn from(headers: [(K, V); N]) -> Self {
+    headers.into_iter().collect()
+  }
+}
+
+impl<'a> IntoIterator for &'a Headers {
+  type Item = (&'a str, &'a str);
+  type IntoIter = Box<dyn Iterator<Item = Self::Item> + 'a>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    Box::new(self.iter())
+  }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum HttpError<T: Error> {
+  /// A non-`2xx` response, with as much of its body as could be read.
   #[error("status code: {status}")]
-  Status { status: u16, headers: Headers },
+  Status {
+    status: u16,
+    headers: Headers,
+    body: Vec<u8>,
+  },
   #[error("request: {0}")]
   Client(T),
+  /// An I/O error, the timed out ones are reported as [`HttpError::Timeout`] instead.
   #[error("I/O: {0}")]
-  IO(#[from] io::Error),
+  IO(#[source] io::Error),
+  #[error("rate limited, retry in {retry_after:?}")]
+  RateLimited { retry_after: Duration },
+  /// The request didn't complete before its timeout, see [`HttpRequest::with_timeout`].
+  #[error("timed out")]
+  Timeout,
 }
 
 impl<T: Error> HttpError<T> {
-  pub fn from_status(status: u16, headers: Headers) -> Self {
-    HttpError::Status { status, headers }
+  pub fn from_status(status: u16, headers: Headers, body: Vec<u8>) -> Self {
+    HttpError::Status {
+      status,
+      headers,
+      body,
+    }
   }
 
   pub fn from_client(err: T) -> Self {
@@ -29,18 +177,320 @@ impl<T: Error> HttpError<T> {
   }
 }
 
+impl<T: Error> From<io::Error> for HttpError<T> {
+  fn from(err: io::Error) -> Self {
+    match err.kind() {
+      io::ErrorKind::TimedOut => HttpError::Timeout,
+      _ => HttpError::IO(err),
+    }
+  }
+}
+
+#[cfg(feature = "pinning")]
+impl<T: Error + 'static> HttpError<T> {
+  /// The pin mismatch which failed the TLS handshake, if that's what happened.
+  pub fn pin_mismatch(&self) -> Option<&crate::pinning::PinMismatch> {
+    match self {
+      HttpError::Client(err) => crate::pinning::PinMismatch::find(err),
+      HttpError::IO(err) => crate::pinning::PinMismatch::find(err),
+      _ => None,
+    }
+  }
+}
+
+/// HTTP methods used by the `BaseHttpClient` requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Method {
+  Get,
+  Head,
+  Post,
+  Put,
+  Patch,
+  Delete,
+  Options,
+}
+
+impl Method {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Method::Get => "GET",
+      Method::Head => "HEAD",
+      Method::Post => "POST",
+      Method::Put => "PUT",
+      Method::Patch => "PATCH",
+      Method::Delete => "DELETE",
+      Method::Options => "OPTIONS",
+    }
+  }
+
+  /// Whether sending the request more than once has the same effect as sending it once.
+  pub fn is_idempotent(&self) -> bool {
+    !matches!(self, Method::Post | Method::Patch)
+  }
+}
+
+impl fmt::Display for Method {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+/// A part of a [`Multipart`] body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "cassette", derive(serde::Serialize, serde::Deserialize))]
+pub struct Part {
+  pub name: String,
+  pub filename: Option<String>,
+  pub content_type: Option<String>,
+  pub data: Vec<u8>,
+}
+
+/// A `multipart/form-data` body, made of text fields and files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cassette", derive(serde::Serialize, serde::Deserialize))]
+pub struct Multipart {
+  pub parts: Vec<Part>,
+}
+
+impl Multipart {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn with_text(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+    self.parts.push(Part {
+      name: name.into(),
+      filename: None,
+      content_type: None,
+      data: value.into().into_bytes(),
+    });
+    self
+  }
+
+  pub fn with_file(
+    mut self,
+    name: impl Into<String>,
+    filename: impl Into<String>,
+    content_type: impl Into<String>,
+    data: impl Into<Vec<u8>>,
+  ) -> Self {
+    self.parts.push(Part {
+      name: name.into(),
+      filename: Some(filename.into()),
+      content_type: Some(content_type.into()),
+      data: data.into(),
+    });
+    self
+  }
+
+  fn encode(&self, boundary: &str) -> Vec<u8> {
+    // quotes and line breaks can't appear in the quoted names, the way browsers escape them
+    let quote = |value: &str| {
+      value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+    };
+
+    let mut body = vec![];
+    for part in &self.parts {
+      body.extend_from_slice(format!("--{boundary}\r\n").as_bytes());
+      let mut disposition = format!(
+        "Content-Disposition: form-data; name=\"{}\"",
+        quote(&part.name)
+      );
+      if let Some(filename) = &part.filename {
+        disposition.push_str(&format!("; filename=\"{}\"", quote(filename)));
+      }
+      body.extend_from_slice(disposition.as_bytes());
+      if let Some(content_type) = &part.content_type {
+        body.extend_from_slice(format!("\r\nContent-Type: {content_type}").as_bytes());
+      }
+      body.extend_from_slice(b"\r\n\r\n");
+      body.extend_from_slice(&part.data);
+      body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+    body
+  }
+}
+
+/// The body of an [`HttpRequest`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Body {
+  #[default]
+  Empty,
+  Json(Value),
+  Form(Vec<(String, String)>),
+  /// Raw bytes, sent with the `Content-Type` header of the request, if any.
+  Bytes(Vec<u8>),
+  Multipart(Multipart),
+}
+
+impl Body {
+  pub fn is_empty(&self) -> bool {
+    matches!(self, Body::Empty)
+  }
+
+  /// Encodes the body, along with the content type it has to be sent with unless the
+  /// request sets one itself.
+  pub(crate) fn encode(&self) -> (Option<String>, Vec<u8>) {
+    match self {
+      Body::Empty => (None, vec![]),
+      Body::Json(value) => (
+        Some("application/json".to_string()),
+        value.to_string().into_bytes(),
+      ),
+      Body::Form(pairs) => {
+        let form = form_urlencoded::Serializer::new(String::new())
+          .extend_pairs(pairs)
+          .finish();
+        (
+          Some("application/x-www-form-urlencoded".to_string()),
+          form.into_bytes(),
+        )
+      }
+      Body::Bytes(bytes) => (None, bytes.clone()),
+      Body::Multipart(multipart) => {
+        let boundary = uuid::Uuid::new_v4().simple().to_string();
+        (
+          Some(format!("multipart/form-data; boundary={boundary}")),
+          multipart.encode(&boundary),
+        )
+      }
+    }
+  }
+}
+
+/// A request sent with [`BaseHttpClient::send`](crate::nonblocking::BaseHttpClient::send),
+/// which the other methods of the trait are shortcuts for.
+///
+/// The query parameters are appended to the query string already present in the url.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpRequest {
+  pub method: Method,
+  pub url: String,
+  pub query: Vec<(String, String)>,
+  pub headers: Headers,
+  pub body: Body,
+  /// Overrides the timeout of the client for this request, see
+  /// [`HttpRequest::with_timeout`].
+  pub timeout: Option<Duration>,
+}
+
+impl HttpRequest {
+  pub fn new(method: Method, url: impl Into<String>) -> Self {
+    HttpRequest {
+      method,
+      url: url.into(),
+      query: vec![],
+      headers: Headers::new(),
+      body: Body::Empty,
+      timeout: None,
+    }
+  }
+
+  pub fn with_query(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+    self.query.push((key.into(), value.into()));
+    self
+  }
+
+  pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+    self.headers.insert(name.into(), value.into());
+    self
+  }
+
+  pub fn with_json(mut self, value: Value) -> Self {
+    self.body = Body::Json(value);
+    self
+  }
+
+  pub fn with_form<I, K, V>(mut self, pairs: I) -> Self
+  where
+    I: IntoIterator<Item = (K, V)>,
+    K: Into<String>,
+    V: Into<String>,
+  {
+    let pairs = pairs
+      .into_iter()
+      .map(|(key, value)| (key.into(), value.into()))
+      .collect();
+    self.body = Body::Form(pairs);
+    self
+  }
+
+  pub fn with_bytes(mut self, bytes: impl Into<Vec<u8>>) -> Self {
+    self.body = Body::Bytes(bytes.into());
+    self
+  }
+
+  pub fn with_multipart(mut self, multipart: Multipart) -> Self {
+    self.body = Body::Multipart(multipart);
+    self
+  }
+
+  /// Fails the request with [`HttpError::Timeout`] when it isn't complete after the given
+  /// duration, from connecting to reading the whole body, instead of the timeout of the
+  /// client.
+  ///
+  /// The body of a streaming response is covered as well, except by `HyperClient` which
+  /// stops the timer once the headers are received.
+  pub fn with_timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = Some(timeout);
+    self
+  }
+
+  /// The url with the query parameters appended, or the url as is when it can't be parsed.
+  pub fn full_url(&self) -> String {
+    if self.query.is_empty() {
+      return self.url.clone();
+    }
+
+    match Url::parse(&self.url) {
+      Ok(mut url) => {
+        url.query_pairs_mut().extend_pairs(&self.query);
+        url.into()
+      }
+      Err(_) => self.url.clone(),
+    }
+  }
+
+  /// Builds the request of one of the shortcut methods of `BaseHttpClient`.
+  pub(crate) fn from_parts(
+    method: Method,
+    url: &str,
+    headers: Option<&Headers>,
+    query: &Query,
+    body: Body,
+  ) -> Self {
+    HttpRequest {
+      method,
+      url: url.to_owned(),
+      query: query
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect(),
+      headers: headers.cloned().unwrap_or_default(),
+      body,
+      timeout: None,
+    }
+  }
+}
+
+/// A response with its whole body read.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HttpResponse {
   status: u16,
   headers: Headers,
-  body: String,
+  body: Vec<u8>,
 }
 
 impl HttpResponse {
-  pub fn new(status: u16, headers: Headers, body: String) -> Self {
+  pub fn new(status: u16, headers: Headers, body: impl Into<Vec<u8>>) -> Self {
     HttpResponse {
       status,
       headers,
-      body,
+      body: body.into(),
     }
   }
 
@@ -51,64 +501,195 @@ impl HttpResponse {
   pub fn headers(&self) -> &Headers {
     &self.headers
   }
-  pub fn body(&self) -> &str {
+
+  /// The body as text, with the invalid UTF-8 sequences replaced.
+  pub fn body(&self) -> Cow<'_, str> {
+    String::from_utf8_lossy(&self.body)
+  }
+
+  pub fn bytes(&self) -> &[u8] {
     &self.body
   }
+
+  pub fn into_bytes(self) -> Vec<u8> {
+    self.body
+  }
+
+  /// Deserializes the JSON body.
+  pub fn json<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+    serde_json::from_slice(&self.body)
+  }
 }
 
-/// This trait represents the interface to be implemented for an HTTP client,
-/// which is kept separate from the gf-auth client for cleaner code.
-///
-/// When a request doesn't need to pass parameters, the empty or default value
-/// of the payload type should be passed, like `json!({})` or `Query::new()`.
-/// This avoids using `Option<T>` because `Value` itself may be null in other
-/// different ways (`Value::Null`, an empty `Value::Object`...), so this removes
-/// redundancy and edge cases (a `Some(Value::Null), for example, doesn't make
-/// much sense).
-#[maybe_async]
-pub trait BaseHttpClient: Send + Default + Clone + fmt::Debug {
-  type Error;
-
-  async fn get(
-    &self,
-    url: &str,
-    headers: Option<&Headers>,
-    payload: &Query,
-  ) -> Result<HttpResponse, Self::Error>;
+flavoured! {
+  /// A response whose body is read as it is received, returned by
+  /// [`BaseHttpClient::send_streaming`].
+  pub struct StreamingResponse {
+    status: u16,
+    headers: Headers,
+    body: ResponseBody,
+  }
 
-  async fn post(
-    &self,
-    url: &str,
-    headers: Option<&Headers>,
-    payload: &Value,
-  ) -> Result<HttpResponse, Self::Error>;
+  impl StreamingResponse {
+    pub fn new(status: u16, headers: Headers, body: ResponseBody) -> Self {
+      StreamingResponse {
+        status,
+        headers,
+        body,
+      }
+    }
 
-  async fn post_form(
-    &self,
-    url: &str,
-    headers: Option<&Headers>,
-    payload: &Form<'_>,
-  ) -> Result<HttpResponse, Self::Error>;
+    pub fn status(&self) -> u16 {
+      self.status
+    }
 
-  async fn put(
-    &self,
-    url: &str,
-    headers: Option<&Headers>,
-    payload: &Value,
-  ) -> Result<HttpResponse, Self::Error>;
+    pub fn headers(&self) -> &Headers {
+      &self.headers
+    }
 
-  async fn delete(
-    &self,
-    url: &str,
-    headers: Option<&Headers>,
-    payload: &Value,
-  ) -> Result<HttpResponse, Self::Error>;
+    pub fn into_body(self) -> ResponseBody {
+      self.body
+    }
+  }
 
-  async fn options(
-    &self,
-    url: &str,
-    headers: Option<&Headers>,
-  ) -> Result<HttpResponse, Self::Error>;
+  impl From<HttpResponse> for StreamingResponse {
+    fn from(response: HttpResponse) -> Self {
+      StreamingResponse::new(
+        response.status,
+        response.headers,
+        buffered_body(response.body),
+      )
+    }
+  }
+
+  impl fmt::Debug for StreamingResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      f.debug_struct("StreamingResponse")
+        .field("status", &self.status)
+        .field("headers", &self.headers)
+        .finish_non_exhaustive()
+    }
+  }
+
+  /// This trait represents the interface to be implemented for an HTTP client,
+  /// which is kept separate from the gf-auth client for cleaner code.
+  ///
+  /// Clients only implement [`send`](Self::send), the other methods are shortcuts for
+  /// the most common requests.
+  ///
+  /// When a request doesn't need to pass parameters, the empty or default value
+  /// of the payload type should be passed, like `json!({})` or `Query::new()`.
+  /// This avoids using `Option<T>` because `Value` itself may be null in other
+  /// different ways (`Value::Null`, an empty `Value::Object`...), so this removes
+  /// redundancy and edge cases (a `Some(Value::Null), for example, doesn't make
+  /// much sense).
+  ///
+  /// Dropping the future of an asynchronous request cancels it. The cookie jar of the
+  /// client is only updated once the headers of the response are received, so it's left
+  /// as it was before a cancelled request, or with all the cookies of its response.
+  #[maybe_async]
+  pub trait BaseHttpClient: Send + Default + Clone + fmt::Debug {
+    type Error;
+
+    async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Self::Error>;
+
+    /// Sends the request without reading the body, which is then read from the response
+    /// as it is received. Non-`2xx` responses are still returned as errors.
+    ///
+    /// Clients which can't stream the body, such as the wrappers recording the requests,
+    /// read the whole body with [`send`](Self::send) first.
+    async fn send_streaming(&self, request: &HttpRequest) -> Result<StreamingResponse, Self::Error> {
+      Ok(self.send(request).await?.into())
+    }
+
+    async fn get(
+      &self,
+      url: &str,
+      headers: Option<&Headers>,
+      payload: &Query,
+    ) -> Result<HttpResponse, Self::Error> {
+      let request = HttpRequest::from_parts(Method::Get, url, headers, payload, Body::Empty);
+      self.send(&request).await
+    }
+
+    async fn post(
+      &self,
+      url: &str,
+      headers: Option<&Headers>,
+      payload: &Value,
+    ) -> Result<HttpResponse, Self::Error> {
+      let body = Body::Json(payload.clone());
+      let request = HttpRequest::from_parts(Method::Post, url, headers, &Query::new(), body);
+      self.send(&request).await
+    }
+
+    async fn post_form(
+      &self,
+      url: &str,
+      headers: Option<&Headers>,
+      payload: &Form<'_>,
+    ) -> Result<HttpResponse, Self::Error> {
+      let form = payload
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+      let request =
+        HttpRequest::from_parts(Method::Post, url, headers, &Query::new(), Body::Form(form));
+      self.send(&request).await
+    }
+
+    async fn put(
+      &self,
+      url: &str,
+      headers: Option<&Headers>,
+      payload: &Value,
+    ) -> Result<HttpResponse, Self::Error> {
+      let body = Body::Json(payload.clone());
+      let request = HttpRequest::from_parts(Method::Put, url, headers, &Query::new(), body);
+      self.send(&request).await
+    }
+
+    async fn patch(
+      &self,
+      url: &str,
+      headers: Option<&Headers>,
+      payload: &Value,
+    ) -> Result<HttpResponse, Self::Error> {
+      let body = Body::Json(payload.clone());
+      let request = HttpRequest::from_parts(Method::Patch, url, headers, &Query::new(), body);
+      self.send(&request).await
+    }
+
+    async fn delete(
+      &self,
+      url: &str,
+      headers: Option<&Headers>,
+      payload: &Value,
+    ) -> Result<HttpResponse, Self::Error> {
+      let body = Body::Json(payload.clone());
+      let request = HttpRequest::from_parts(Method::Delete, url, headers, &Query::new(), body);
+      self.send(&request).await
+    }
+
+    async fn head(
+      &self,
+      url: &str,
+      headers: Option<&Headers>,
+    ) -> Result<HttpResponse, Self::Error> {
+      let request = HttpRequest::from_parts(Method::Head, url, headers, &Query::new(), Body::Empty);
+      self.send(&request).await
+    }
+
+    async fn options(
+      &self,
+      url: &str,
+      headers: Option<&Headers>,
+    ) -> Result<HttpResponse, Self::Error> {
+      let request =
+        HttpRequest::from_parts(Method::Options, url, headers, &Query::new(), Body::Empty);
+      self.send(&request).await
+    }
+  }
 }
 
 pub trait CustomCertHttpClient {
@@ -118,3 +699,120 @@ pub trait CustomCertHttpClient {
     CLIENT: AsRef<[u8]>,
     KEY: AsRef<[u8]>;
 }
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+
+  use super::{Body, Headers, HttpError, HttpRequest, HttpResponse, Method, Multipart};
+
+  #[test]
+  fn appends_query_to_url() {
+    let request = HttpRequest::new(Method::Get, "https://spark.gameforge.com/api?locale=en")
+      .with_query("page", "2")
+      .with_query("filter", "a b");
+    assert_eq!(
+      request.full_url(),
+      "https://spark.gameforge.com/api?locale=en&page=2&filter=a+b"
+    );
+
+    let request = HttpRequest::new(Method::Get, "not a url").with_query("page", "2");
+    assert_eq!(request.full_url(), "not a url");
+  }
+
+  #[test]
+  fn encodes_bodies() {
+    let form = Body::Form(vec![("user".to_string(), "a&b".to_string())]);
+    assert_eq!(
+      form.encode(),
+      (
+        Some("application/x-www-form-urlencoded".to_string()),
+        b"user=a%26b".to_vec()
+      )
+    );
+    assert_eq!(Body::Bytes(vec![1, 2]).encode(), (None, vec![1, 2]));
+
+    let multipart = Multipart::new().with_text("na\"me", "value").with_file(
+      "avatar",
+      "me.png",
+      "image/png",
+      *b"png",
+    );
+    let (content_type, body) = Body::Multipart(multipart).encode();
+    let boundary = content_type
+      .unwrap()
+      .strip_prefix("multipart/form-data; boundary=")
+      .unwrap()
+      .to_string();
+    assert_eq!(
+      String::from_utf8(body).unwrap(),
+      format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"na%22me\"\r\n\r\nvalue\r\n\
+        --{boundary}\r\nContent-Disposition: form-data; name=\"avatar\"; filename=\"me.png\"\r\n\
+        Content-Type: image/png\r\n\r\npng\r\n--{boundary}--\r\n"
+      )
+    );
+  }
+
+  #[test]
+  fn reads_binary_and_json_bodies() {
+    let response = HttpResponse::new(200, Headers::new(), vec![b'o', b'k', 0xff]);
+    assert_eq!(response.bytes(), [b'o', b'k', 0xff]);
+    assert_eq!(response.body(), "ok\u{fffd}");
+
+    let response = HttpResponse::new(200, Headers::new(), r#"{"token":"abc"}"#);
+    let json = response.json::<HashMap<String, String>>().unwrap();
+    assert_eq!(json["token"], "abc");
+    assert!(response.json::<Vec<u8>>().is_err());
+  }
+
+  #[test]
+  fn headers_are_case_insensitive_and_ordered() {
+    let mut headers = Headers::from([("Set-Cookie", "a=1"), ("Accept", "*/*")]);
+    headers.append("set-cookie", "b=2");
+    assert_eq!(headers.get("SET-COOKIE"), Some("a=1"));
+    assert_eq!(
+      headers.get_all("set-cookie").collect::<Vec<_>>(),
+      ["a=1", "b=2"]
+    );
+
+    headers.insert("accept", "text/html");
+    headers.insert("SET-COOKIE", "c=3");
+    headers.insert("User-Agent", "launcher");
+    assert_eq!(
+      headers.iter().collect::<Vec<_>>(),
+      [
+        ("SET-COOKIE", "c=3"),
+        ("accept", "text/html"),
+        ("User-Agent", "launcher")
+      ]
+    );
+
+    assert_eq!(headers.remove("user-agent"), Some("launcher".to_string()));
+    assert!(!headers.contains_key("User-Agent"));
+    assert_eq!(headers.len(), 2);
+  }
+
+  #[test]
+  fn method_names() {
+    assert_eq!(Method::Get.to_string(), "GET");
+    assert_eq!(Method::Options.as_str(), "OPTIONS");
+    assert_eq!(Method::Patch.as_str(), "PATCH");
+    assert!(Method::Head.is_idempotent());
+    assert!(!Method::Patch.is_idempotent());
+  }
+
+  #[test]
+  fn reports_timed_out_io_errors_as_timeouts() {
+    let timed_out = std::io::Error::from(std::io::ErrorKind::TimedOut);
+    let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+    assert!(matches!(
+      HttpError::<std::io::Error>::from(timed_out),
+      HttpError::Timeout
+    ));
+    assert!(matches!(
+      HttpError::<std::io::Error>::from(reset),
+      HttpError::IO(_)
+    ));
+  }
+}
@@ -0,0 +1,160 @@
+use std::convert::Infallible;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use cookie_store::{Cookie, CookieStore};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use url::Url;
+
+use crate::common::Headers;
+
+/// Cookies kept across the requests of every client sharing the jar, see the
+/// `with_cookie_jar` method of the backends.
+///
+/// Cloning the jar shares its cookies. Serializing it stores every unexpired cookie,
+/// session cookies included, so a restored jar resumes the same session.
+#[derive(Clone, Default)]
+pub struct CookieJar {
+  store: Arc<RwLock<CookieStore>>,
+}
+
+impl CookieJar {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Returns the value of the cookie with the given name that would be sent to `url`.
+  pub fn get(&self, url: &str, name: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let store = self.store.read().unwrap();
+    let value = store
+      .get_request_values(&url)
+      .find(|(cookie, _)| *cookie == name)
+      .map(|(_, value)| value.to_string());
+    value
+  }
+
+  /// Stores a cookie as if it was received in a `Set-Cookie` header of a response from `url`.
+  pub fn insert(&self, url: &str, set_cookie: &str) {
+    self.store_response(url, [set_cookie]);
+  }
+
+  pub fn clear(&self) {
+    self.store.write().unwrap().clear();
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.store.read().unwrap().iter_unexpired().next().is_none()
+  }
+
+  /// Value of the `Cookie` header for a request to `url`, unless the request already
+  /// sets the header itself.
+  pub(crate) fn request_header(&self, url: &str, headers: Option<&Headers>) -> Option<String> {
+    if headers.is_some_and(|headers| headers.contains_key("cookie")) {
+      return None;
+    }
+
+    let url = Url::parse(url).ok()?;
+    let store = self.store.read().unwrap();
+    let header = store
+      .get_request_values(&url)
+      .map(|(name, value)| format!("{name}={value}"))
+      .collect::<Vec<_>>()
+      .join("; ");
+    (!header.is_empty()).then_some(header)
+  }
+
+  /// Stores the values of the `Set-Cookie` headers of a response from `url`.
+  pub(crate) fn store_response<'a, I>(&self, url: &str, set_cookies: I)
+  where
+    I: IntoIterator<Item = &'a str>,
+  {
+    let Ok(url) = Url::parse(url) else {
+      return;
+    };
+    let mut store = self.store.write().unwrap();
+    for set_cookie in set_cookies {
+      if let Err(err) = store.parse(set_cookie, &url) {
+        log::debug!("ignoring cookie from {url}: {err}");
+      }
+    }
+  }
+}
+
+impl fmt::Debug for CookieJar {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    // cookie values are secrets, only their names are shown
+    let store = self.store.read().unwrap();
+    f.debug_set()
+      .entries(store.iter_unexpired().map(|cookie| cookie.name()))
+      .finish()
+  }
+}
+
+impl Serialize for CookieJar {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let store = self.store.read().unwrap();
+    serializer.collect_seq(store.iter_unexpired())
+  }
+}
+
+impl<'de> Deserialize<'de> for CookieJar {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let cookies = Vec::<Cookie<'static>>::deserialize(deserializer)?;
+    let store = CookieStore::from_cookies(cookies.into_iter().map(Ok::<_, Infallible>), false)
+      // the iterator never yields an error
+      .unwrap();
+    Ok(Self {
+      store: Arc::new(RwLock::new(store)),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::CookieJar;
+  use crate::common::Headers;
+
+  const URL: &str = "https://spark.gameforge.com/api/v1/auth/sessions";
+
+  #[test]
+  fn sends_cookies_matching_the_url() {
+    let jar = CookieJar::new();
+    jar.insert(URL, "session=abc; Path=/api; Secure");
+    jar.insert(URL, "locale=en; Path=/");
+
+    let header = jar.request_header(URL, None).unwrap();
+    assert!(header.contains("session=abc"));
+    assert!(header.contains("locale=en"));
+    assert_eq!(
+      jar.request_header("https://spark.gameforge.com/", None),
+      Some("locale=en".to_string())
+    );
+    assert_eq!(
+      jar.request_header("http://spark.gameforge.com/api", None),
+      Some("locale=en".to_string())
+    );
+    assert_eq!(jar.request_header("https://example.com/", None), None);
+
+    let headers = Headers::from([("Cookie".to_string(), "custom=1".to_string())]);
+    assert_eq!(jar.request_header(URL, Some(&headers)), None);
+  }
+
+  #[test]
+  fn round_trips_session_cookies() {
+    let jar = CookieJar::new();
+    jar.insert(URL, "session=abc; Path=/");
+    jar.insert(URL, "expired=1; Path=/; Max-Age=0");
+
+    let json = serde_json::to_string(&jar).unwrap();
+    let restored = serde_json::from_str::<CookieJar>(&json).unwrap();
+
+    assert_eq!(restored.get(URL, "session"), Some("abc".to_string()));
+    assert_eq!(restored.get(URL, "expired"), None);
+    assert_eq!(format!("{restored:?}"), r#"{"session"}"#);
+
+    restored.clear();
+    assert!(restored.is_empty());
+    assert!(!jar.is_empty());
+  }
+}
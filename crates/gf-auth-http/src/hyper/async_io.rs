@@ -0,0 +1,285 @@
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_net::TcpStream;
+use futures_lite::{AsyncRead, AsyncWrite};
+use futures_rustls::TlsConnector;
+use http_body_util::Full;
+use hyper::body::Bytes;
+use hyper::client::conn::http1;
+use hyper::header::{HeaderValue, HOST};
+use hyper::http::uri::Scheme;
+use hyper::{Request, Uri};
+use maybe_async::must_be_async;
+use rustls::pki_types::ServerName;
+use rustls::ClientConfig;
+use smol_hyper::rt::FuturesIo;
+
+use crate::common::{CustomCertHttpClient, HttpError, HttpRequest, HttpResponse};
+use crate::config::{ConfigError, FromConfig, HttpClientConfig, HttpVersion};
+#[cfg(feature = "cookies")]
+use crate::cookies::CookieJar;
+#[cfg(feature = "cookies")]
+use crate::hyper::{add_cookies, utils::set_cookies};
+use crate::hyper::{
+  build_request, custom_cert_tls_config, native_tls_config, read_response, with_timeout,
+  HyperClientError,
+};
+use crate::logging::RequestLogger;
+use crate::nonblocking::BaseHttpClient;
+
+/// Async client built on top of `hyper` and `async-io`, which runs on any async runtime,
+/// such as tokio, async-std or smol.
+///
+/// Every request opens a new HTTP/1.1 connection, which is driven by the task awaiting
+/// the request, so no executor has to be provided. For the same reason, streamed responses
+/// are read whole before being returned. Proxies and local addresses are not supported.
+#[derive(Debug, Clone)]
+pub struct AsyncIoClient {
+  tls: Arc<ClientConfig>,
+  timeout: Option<Duration>,
+  nodelay: bool,
+  logger: RequestLogger,
+  #[cfg(feature = "cookies")]
+  cookies: Option<CookieJar>,
+}
+
+impl Default for AsyncIoClient {
+  fn default() -> Self {
+    Self::from_config(&HttpClientConfig::new()).expect("Failed to initialize TLS")
+  }
+}
+
+impl FromConfig for AsyncIoClient {
+  fn from_config(config: &HttpClientConfig) -> Result<Self, ConfigError> {
+    if !config.resolve.is_empty() {
+      return Err(ConfigError::Unsupported("DNS override"));
+    }
+    if config.proxy.is_some() {
+      return Err(ConfigError::Unsupported("proxy"));
+    }
+    if config.local_address.is_some() {
+      return Err(ConfigError::Unsupported("local address"));
+    }
+    if config.connection.tcp_keepalive.is_some() {
+      return Err(ConfigError::Unsupported("TCP keepalive"));
+    }
+    if config.connection.http_version == HttpVersion::Http2PriorKnowledge {
+      return Err(ConfigError::Unsupported("HTTP/2"));
+    }
+
+    let tls = match &config.custom_cert {
+      Some(cert) => custom_cert_tls_config(cert, config)?,
+      None => native_tls_config(config)?,
+    };
+    Ok(Self {
+      tls: Arc::new(tls),
+      timeout: config.timeout,
+      nodelay: config.connection.tcp_nodelay.unwrap_or(true),
+      logger: RequestLogger::default(),
+      #[cfg(feature = "cookies")]
+      cookies: None,
+    })
+  }
+}
+
+impl AsyncIoClient {
+  /// Replaces the logger used for outgoing requests, see [`RequestLogger`].
+  pub fn with_request_logger(mut self, logger: RequestLogger) -> Self {
+    self.logger = logger;
+    self
+  }
+
+  /// Stores the cookies set by the responses in the given jar and sends them back with
+  /// the following requests, see [`CookieJar`].
+  #[cfg(feature = "cookies")]
+  pub fn with_cookie_jar(mut self, jar: CookieJar) -> Self {
+    self.cookies = Some(jar);
+    self
+  }
+
+  async fn exchange<IO>(
+    &self,
+    io: IO,
+    request: Request<Full<Bytes>>,
+    method: &str,
+    url: &str,
+    started: Instant,
+  ) -> Result<HttpResponse, HttpError<HyperClientError>>
+  where
+    IO: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+  {
+    let (mut sender, connection) = http1::Builder::new()
+      .title_case_headers(true)
+      .handshake(FuturesIo::new(io))
+      .await?;
+
+    let exchange = async move {
+      let response = sender.send_request(request).await?;
+      #[cfg(feature = "cookies")]
+      if let Some(jar) = &self.cookies {
+        jar.store_response(url, set_cookies(response.headers()));
+      }
+      self
+        .logger
+        .log_response(method, url, response.status().as_u16(), started.elapsed());
+      read_response(response).await
+    };
+
+    // The connection is driven alongside the request and finishes once the sender
+    // is dropped, so the result of the exchange is the only one that matters.
+    let (response, _) = futures_lite::future::zip(exchange, connection).await;
+    response
+  }
+
+  async fn connect_and_send(
+    &self,
+    request: &HttpRequest,
+  ) -> Result<HttpResponse, HttpError<HyperClientError>> {
+    let url = request.url.as_str();
+    let method = request.method.as_str();
+    let mut built = build_request(request)?;
+    #[cfg(feature = "cookies")]
+    if let Some(jar) = &self.cookies {
+      add_cookies(jar, &mut built, url, &request.headers)?;
+    }
+    let uri = built.uri().clone();
+    let host = uri
+      .host()
+      .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "url without a host"))?;
+    let https = uri.scheme() == Some(&Scheme::HTTPS);
+
+    // HTTP/1.1 requests are sent in origin-form, with the authority in the `Host` header
+    let authority = match uri.port() {
+      Some(port) => format!("{host}:{port}"),
+      None => host.to_string(),
+    };
+    built.headers_mut().insert(
+      HOST,
+      HeaderValue::from_str(&authority).map_err(hyper::http::Error::from)?,
+    );
+    *built.uri_mut() = match uri.path_and_query() {
+      Some(path) => Uri::from(path.clone()),
+      None => Uri::from_static("/"),
+    };
+
+    self.logger.log_request(request);
+    let started = Instant::now();
+
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = uri.port_u16().unwrap_or(if https { 443 } else { 80 });
+    let stream = TcpStream::connect((host, port)).await?;
+    stream.set_nodelay(self.nodelay)?;
+
+    if https {
+      let server_name = ServerName::try_from(host.to_string())?;
+      let stream = TlsConnector::from(self.tls.clone())
+        .connect(server_name, stream)
+        .await?;
+      self.exchange(stream, built, method, url, started).await
+    } else {
+      self.exchange(stream, built, method, url, started).await
+    }
+  }
+}
+
+#[must_be_async]
+impl BaseHttpClient for AsyncIoClient {
+  type Error = HttpError<HyperClientError>;
+
+  async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Self::Error> {
+    let timeout = request.timeout.or(self.timeout);
+    with_timeout(timeout, self.connect_and_send(request)).await
+  }
+}
+
+impl CustomCertHttpClient for AsyncIoClient {
+  fn with_custom_cert<CA, CLIENT, KEY>(ca: &CA, client: &CLIENT, key: &KEY) -> Self
+  where
+    CA: AsRef<[u8]>,
+    CLIENT: AsRef<[u8]>,
+    KEY: AsRef<[u8]>,
+  {
+    let config = HttpClientConfig::new().with_custom_cert(ca, client, key);
+    Self::from_config(&config).expect("Failed to initialize TLS")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::{AsyncIoClient, HyperClientError};
+  use crate::common::{CustomCertHttpClient, HttpError, Query};
+  use crate::nonblocking::BaseHttpClient;
+  use crate::testing::serve_once;
+
+  #[test]
+  fn runs_on_smol() {
+    let (url, server) = serve_once("HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok");
+
+    let response = smol::block_on(AsyncIoClient::default().post(
+      &format!("{url}/api?v=1"),
+      None,
+      &json!({ "key": "value" }),
+    ))
+    .unwrap();
+    assert_eq!(response.body(), "ok");
+
+    let request = server.join().unwrap();
+    assert!(request.starts_with("POST /api?v=1 HTTP/1.1\r\n"));
+    assert!(request.contains(&format!("Host: {}\r\n", &url["http://".len()..])));
+    assert!(request.ends_with(r#"{"key":"value"}"#));
+  }
+
+  #[test]
+  fn times_out_requests_outside_of_tokio() {
+    use std::time::Duration;
+
+    use crate::config::{FromConfig, HttpClientConfig};
+    use crate::testing::serve_never;
+
+    let (url, _listener) = serve_never();
+    let config = HttpClientConfig::new().with_timeout(Duration::from_millis(100));
+    let client = AsyncIoClient::from_config(&config).unwrap();
+
+    let result = smol::block_on(client.get(&url, None, &Query::new()));
+    assert!(matches!(result, Err(HttpError::Timeout)));
+  }
+
+  #[test]
+  fn runs_on_async_std() {
+    let (url, server) = serve_once("HTTP/1.1 429 Too Many Requests\r\ncontent-length: 0\r\n\r\n");
+
+    let result = async_std::task::block_on(AsyncIoClient::default().get(
+      &url,
+      None,
+      &Query::from([("page", "1")]),
+    ));
+    assert!(matches!(result, Err(HttpError::Status { status: 429, .. })));
+    assert!(server
+      .join()
+      .unwrap()
+      .starts_with("GET /?page=1 HTTP/1.1\r\n"));
+  }
+
+  #[test]
+  fn create_client_with_custom_cert_rustls_tls() -> Result<(), HttpError<HyperClientError>> {
+    println!("Testing async-io with rustls-tls");
+    let client = AsyncIoClient::with_custom_cert(
+      include_bytes!("../../../../resources/ca.pem"),
+      include_bytes!("../../../../resources/client.pem"),
+      include_bytes!("../../../../resources/key.pem"),
+    );
+
+    let response = smol::block_on(client.get(
+      "https://events2.gameforge.com/",
+      Default::default(),
+      &Default::default(),
+    ))?;
+
+    assert_eq!(response.status(), 200);
+    Ok(())
+  }
+}
@@ -0,0 +1,92 @@
+use std::error::Error as StdError;
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use hyper::Uri;
+use hyper_util::client::legacy::connect::proxy::{SocksV5, Tunnel};
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::proxy::matcher::Matcher;
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpStream;
+use tower_service::Service;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// Connects to the destination either directly or through the proxy matching it, read
+/// from the client configuration or the environment.
+///
+/// HTTP proxies are used through `CONNECT` tunnels, SOCKS5 proxies resolve the
+/// destination locally for `socks5://` and on the proxy for `socks5h://`.
+#[derive(Clone)]
+pub(crate) struct ProxyConnector {
+  http: HttpConnector,
+  proxies: Arc<Matcher>,
+}
+
+impl ProxyConnector {
+  pub(crate) fn new(http: HttpConnector, proxies: Matcher) -> Self {
+    Self {
+      http,
+      proxies: Arc::new(proxies),
+    }
+  }
+}
+
+async fn connect<S>(mut service: S, dst: Uri) -> Result<TokioIo<TcpStream>, BoxError>
+where
+  S: Service<Uri, Response = TokioIo<TcpStream>>,
+  S::Error: Into<BoxError>,
+{
+  poll_fn(|cx| service.poll_ready(cx))
+    .await
+    .map_err(Into::into)?;
+  service.call(dst).await.map_err(Into::into)
+}
+
+/// Makes the port of the destination explicit, as tunnels default to 443 otherwise.
+fn with_port(dst: Uri) -> Uri {
+  if dst.port().is_some() {
+    return dst;
+  }
+  let (Some(scheme), Some(host)) = (dst.scheme_str(), dst.host()) else {
+    return dst;
+  };
+  let port = if scheme == "http" { 80 } else { 443 };
+  format!("{scheme}://{host}:{port}").parse().unwrap_or(dst)
+}
+
+impl Service<Uri> for ProxyConnector {
+  type Response = TokioIo<TcpStream>;
+  type Error = BoxError;
+  type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+  fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    Poll::Ready(Ok(()))
+  }
+
+  fn call(&mut self, dst: Uri) -> Self::Future {
+    let http = self.http.clone();
+    let Some(proxy) = self.proxies.intercept(&dst) else {
+      return Box::pin(connect(http, dst));
+    };
+
+    match proxy.uri().scheme_str() {
+      Some(scheme @ ("socks5" | "socks5h")) => {
+        let mut socks = SocksV5::new(proxy.uri().clone(), http).local_dns(scheme == "socks5");
+        if let Some((user, pass)) = proxy.raw_auth() {
+          socks = socks.with_auth(user.to_string(), pass.to_string());
+        }
+        Box::pin(connect(socks, dst))
+      }
+      _ => {
+        let mut tunnel = Tunnel::new(proxy.uri().clone(), http);
+        if let Some(auth) = proxy.basic_auth() {
+          tunnel = tunnel.with_auth(auth.clone());
+        }
+        Box::pin(connect(tunnel, with_port(dst)))
+      }
+    }
+  }
+}
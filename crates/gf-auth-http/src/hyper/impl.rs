@@ -0,0 +1,8 @@
+use crate::common::HttpError;
+use crate::hyper::HyperClientError;
+
+impl<E: Into<HyperClientError>> From<E> for HttpError<HyperClientError> {
+  fn from(error: E) -> Self {
+    HttpError::Client(error.into())
+  }
+}
@@ -0,0 +1,200 @@
+#[cfg(feature = "client-async-io")]
+mod async_io;
+#[cfg(feature = "client-hyper")]
+mod connector;
+mod r#impl;
+#[cfg(feature = "client-hyper")]
+mod tokio;
+mod utils;
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+#[cfg(feature = "cookies")]
+use hyper::header::COOKIE;
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::{Request, Response};
+use rustls::client::WantsClientCert;
+use rustls::{ClientConfig, ConfigBuilder, RootCertStore, WantsVerifier};
+use url::Url;
+
+#[cfg(feature = "cookies")]
+use crate::common::Headers;
+use crate::common::{HttpError, HttpRequest, HttpResponse};
+use crate::config::{ConfigError, CustomCert, HttpClientConfig};
+#[cfg(feature = "cookies")]
+use crate::cookies::CookieJar;
+use crate::hyper::utils::{convert_headers, header_map};
+use crate::nonblocking::sleep;
+#[cfg(feature = "pinning")]
+use crate::pinning::with_pinned_roots;
+
+#[cfg(feature = "client-async-io")]
+pub use crate::hyper::async_io::AsyncIoClient;
+#[cfg(feature = "client-hyper")]
+pub use crate::hyper::tokio::HyperClient;
+
+#[derive(thiserror::Error, Debug)]
+pub enum HyperClientError {
+  #[error("invalid url: {0}")]
+  Url(#[from] url::ParseError),
+  #[error("invalid request: {0}")]
+  Http(#[from] hyper::http::Error),
+  #[error("json: {0}")]
+  Json(#[from] serde_json::Error),
+  #[cfg(feature = "client-hyper")]
+  #[error("connection: {0}")]
+  Connection(#[from] hyper_util::client::legacy::Error),
+  #[error("invalid server name: {0}")]
+  ServerName(#[from] rustls::pki_types::InvalidDnsNameError),
+  #[error("body: {0}")]
+  Body(#[from] hyper::Error),
+}
+
+fn tls_config() -> ConfigBuilder<ClientConfig, WantsVerifier> {
+  ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+    .with_safe_default_protocol_versions()
+    // the default protocol versions are supported by the ring provider
+    .unwrap()
+}
+
+/// Trusts the root certificates, also checking the keys pinned in the configuration.
+#[cfg_attr(not(feature = "pinning"), allow(unused_variables))]
+fn with_roots(
+  roots: RootCertStore,
+  config: &HttpClientConfig,
+) -> Result<ConfigBuilder<ClientConfig, WantsClientCert>, ConfigError> {
+  #[cfg(feature = "pinning")]
+  return with_pinned_roots(tls_config(), roots, &config.pins);
+  #[cfg(not(feature = "pinning"))]
+  Ok(tls_config().with_root_certificates(roots))
+}
+
+/// TLS configuration trusting the certificates of the platform.
+pub(crate) fn native_tls_config(config: &HttpClientConfig) -> Result<ClientConfig, ConfigError> {
+  let mut root_store = RootCertStore::empty();
+  let native_certs = rustls_native_certs::load_native_certs().unwrap_or_else(|e| {
+    log::error!("loading native certificates: {}", e);
+    vec![]
+  });
+  let (valid_count, invalid_count) = root_store.add_parsable_certificates(native_certs);
+  if valid_count == 0 && invalid_count > 0 {
+    log::error!(
+      "no valid certificates loaded by rustls-native-certs. all HTTPS requests will fail."
+    );
+  }
+
+  Ok(with_roots(root_store, config)?.with_no_client_auth())
+}
+
+/// TLS configuration for `CustomCertHttpClient`, trusting only the given CA.
+pub(crate) fn custom_cert_tls_config(
+  cert: &CustomCert,
+  config: &HttpClientConfig,
+) -> Result<ClientConfig, ConfigError> {
+  let mut root_store = RootCertStore::empty();
+  root_store.add_parsable_certificates(
+    rustls_pemfile::certs(&mut std::io::Cursor::new(&cert.ca)).flatten(),
+  );
+
+  let private_key = rustls_pemfile::private_key(&mut std::io::Cursor::new(&cert.key))
+    .and_then(|item| item.ok_or(std::io::Error::from(std::io::ErrorKind::UnexpectedEof)))
+    .map_err(|err| ConfigError::Tls(format!("private key: {err}")))?;
+  let client_certs = rustls_pemfile::certs(&mut std::io::Cursor::new(&cert.client))
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|err| ConfigError::Tls(format!("client certificate: {err}")))?;
+
+  with_roots(root_store, config)?
+    .with_client_auth_cert(client_certs, private_key)
+    .map_err(|err| ConfigError::Tls(err.to_string()))
+}
+
+/// Converts the request, with the query appended to the url and the body encoded.
+pub(crate) fn build_request(
+  request: &HttpRequest,
+) -> Result<Request<Full<Bytes>>, HttpError<HyperClientError>> {
+  let url = Url::parse(&request.full_url())?;
+  let (content_type, body) = request.body.encode();
+
+  let mut built = Request::builder()
+    .method(request.method.as_str())
+    .uri(url.as_str())
+    .body(Full::new(Bytes::from(body)))?;
+
+  *built.headers_mut() = header_map(&request.headers)?;
+  if let Some(content_type) = content_type {
+    let content_type = HeaderValue::from_str(&content_type).map_err(hyper::http::Error::from)?;
+    built
+      .headers_mut()
+      .entry(CONTENT_TYPE)
+      .or_insert(content_type);
+  }
+
+  Ok(built)
+}
+
+/// Adds the `Cookie` header with the cookies of the jar for `url`.
+#[cfg(feature = "cookies")]
+pub(crate) fn add_cookies(
+  jar: &CookieJar,
+  request: &mut Request<Full<Bytes>>,
+  url: &str,
+  headers: &Headers,
+) -> Result<(), HttpError<HyperClientError>> {
+  if let Some(cookie) = jar.request_header(url, Some(headers)) {
+    let cookie = HeaderValue::from_str(&cookie).map_err(hyper::http::Error::from)?;
+    request.headers_mut().insert(COOKIE, cookie);
+  }
+  Ok(())
+}
+
+/// Turns the non-`2xx` responses into status errors.
+pub(crate) async fn check_status(
+  response: Response<Incoming>,
+) -> Result<Response<Incoming>, HttpError<HyperClientError>> {
+  if response.status().is_success() {
+    return Ok(response);
+  }
+  let status = response.status().as_u16();
+  let headers = convert_headers(response.headers());
+  // the status is the actual error, so a body that can't be read is left empty
+  let body = response.into_body().collect().await;
+  let body = body.map(|body| body.to_bytes().to_vec());
+  Err(HttpError::from_status(
+    status,
+    headers,
+    body.unwrap_or_default(),
+  ))
+}
+
+/// Reads the body of a successful response, or turns it into a status error.
+pub(crate) async fn read_response(
+  response: Response<Incoming>,
+) -> Result<HttpResponse, HttpError<HyperClientError>> {
+  let response = check_status(response).await?;
+  let status = response.status().as_u16();
+  let headers = convert_headers(response.headers());
+  let body = response.into_body().collect().await?.to_bytes();
+  Ok(HttpResponse::new(status, headers, body.to_vec()))
+}
+
+/// Fails the request with a timeout when it isn't complete after the given duration.
+pub(crate) async fn with_timeout<T, F>(
+  timeout: Option<Duration>,
+  request: F,
+) -> Result<T, HttpError<HyperClientError>>
+where
+  F: Future<Output = Result<T, HttpError<HyperClientError>>>,
+{
+  let Some(timeout) = timeout else {
+    return request.await;
+  };
+  let expired = async {
+    sleep(timeout).await;
+    Err(HttpError::Timeout)
+  };
+  futures_lite::future::or(request, expired).await
+}
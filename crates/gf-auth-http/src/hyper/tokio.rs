@@ -0,0 +1,407 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use futures_lite::StreamExt;
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::Response;
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::client::proxy::matcher::Matcher;
+use hyper_util::rt::{TokioExecutor, TokioTimer};
+use maybe_async::must_be_async;
+use rustls::ClientConfig;
+
+use crate::common::{CustomCertHttpClient, HttpError, HttpRequest, HttpResponse};
+use crate::config::{ConfigError, FromConfig, HttpClientConfig, HttpVersion};
+#[cfg(feature = "cookies")]
+use crate::cookies::CookieJar;
+use crate::hyper::connector::ProxyConnector;
+use crate::hyper::utils::convert_headers;
+#[cfg(feature = "cookies")]
+use crate::hyper::{add_cookies, utils::set_cookies};
+use crate::hyper::{
+  build_request, check_status, custom_cert_tls_config, native_tls_config, read_response,
+  with_timeout, HyperClientError,
+};
+use crate::logging::RequestLogger;
+use crate::nonblocking::{BaseHttpClient, StreamingResponse};
+
+/// Async client built directly on top of `hyper`, with TLS provided by `rustls`.
+///
+/// Connections are pooled per host and negotiate HTTP/2 through ALPN when the server
+/// supports it. Unless a proxy is configured with [`FromConfig`], proxies are read from the
+/// `HTTP_PROXY`, `HTTPS_PROXY`, `ALL_PROXY` and `NO_PROXY` environment variables, like in
+/// the other backends.
+#[derive(Debug, Clone)]
+pub struct HyperClient {
+  client: Client<HttpsConnector<ProxyConnector>, Full<Bytes>>,
+  timeout: Option<Duration>,
+  logger: RequestLogger,
+  #[cfg(feature = "cookies")]
+  cookies: Option<CookieJar>,
+}
+
+impl Default for HyperClient {
+  fn default() -> Self {
+    Self::from_config(&HttpClientConfig::new()).expect("Failed to initialize TLS")
+  }
+}
+
+impl FromConfig for HyperClient {
+  fn from_config(config: &HttpClientConfig) -> Result<Self, ConfigError> {
+    if !config.resolve.is_empty() {
+      return Err(ConfigError::Unsupported("DNS override"));
+    }
+    let proxies = match &config.proxy {
+      // the tunnel to the proxy is not encrypted
+      Some(proxy) if proxy.scheme() == "https" => {
+        return Err(ConfigError::Unsupported("HTTPS proxy"))
+      }
+      Some(proxy) => Matcher::builder().all(proxy.as_str().to_string()).build(),
+      None => Matcher::from_env(),
+    };
+    let tls = match &config.custom_cert {
+      Some(cert) => custom_cert_tls_config(cert, config)?,
+      None => native_tls_config(config)?,
+    };
+
+    Ok(Self {
+      client: build_client(tls, proxies, config),
+      timeout: config.timeout,
+      logger: RequestLogger::default(),
+      #[cfg(feature = "cookies")]
+      cookies: None,
+    })
+  }
+}
+
+fn build_client(
+  tls: ClientConfig,
+  proxies: Matcher,
+  config: &HttpClientConfig,
+) -> Client<HttpsConnector<ProxyConnector>, Full<Bytes>> {
+  let connection = &config.connection;
+  let mut http = HttpConnector::new();
+  http.enforce_http(false);
+  http.set_nodelay(connection.tcp_nodelay.unwrap_or(true));
+  http.set_keepalive(connection.tcp_keepalive);
+  http.set_local_address(config.local_address);
+
+  let builder = HttpsConnectorBuilder::new()
+    .with_tls_config(tls)
+    .https_or_http();
+  let proxied = ProxyConnector::new(http, proxies);
+  let connector = match connection.http_version {
+    HttpVersion::Negotiated => builder
+      .enable_http1()
+      .enable_http2()
+      .wrap_connector(proxied),
+    HttpVersion::Http1Only => builder.enable_http1().wrap_connector(proxied),
+    HttpVersion::Http2PriorKnowledge => builder.enable_http2().wrap_connector(proxied),
+  };
+
+  let mut client = Client::builder(TokioExecutor::new());
+  client
+    .pool_timer(TokioTimer::new())
+    .pool_idle_timeout(connection.idle_timeout.unwrap_or(Duration::from_secs(90)))
+    .http1_title_case_headers(true)
+    .http2_only(connection.http_version == HttpVersion::Http2PriorKnowledge);
+  if let Some(max) = connection.max_idle_per_host {
+    client.pool_max_idle_per_host(max);
+  }
+  client.build(connector)
+}
+
+impl HyperClient {
+  /// Replaces the logger used for outgoing requests, see [`RequestLogger`].
+  pub fn with_request_logger(mut self, logger: RequestLogger) -> Self {
+    self.logger = logger;
+    self
+  }
+
+  /// Stores the cookies set by the responses in the given jar and sends them back with
+  /// the following requests, see [`CookieJar`].
+  #[cfg(feature = "cookies")]
+  pub fn with_cookie_jar(mut self, jar: CookieJar) -> Self {
+    self.cookies = Some(jar);
+    self
+  }
+
+  /// Sends the request, turning the non-`2xx` responses into status errors.
+  async fn exchange(
+    &self,
+    request: &HttpRequest,
+  ) -> Result<Response<Incoming>, HttpError<HyperClientError>> {
+    #[allow(unused_mut)]
+    let mut built = build_request(request)?;
+    #[cfg(feature = "cookies")]
+    if let Some(jar) = &self.cookies {
+      add_cookies(jar, &mut built, &request.url, &request.headers)?;
+    }
+
+    let method = request.method.as_str();
+    self.logger.log_request(request);
+    let started = Instant::now();
+    let response = self.client.request(built).await?;
+    #[cfg(feature = "cookies")]
+    if let Some(jar) = &self.cookies {
+      jar.store_response(&request.url, set_cookies(response.headers()));
+    }
+    self.logger.log_response(
+      method,
+      &request.url,
+      response.status().as_u16(),
+      started.elapsed(),
+    );
+
+    check_status(response).await
+  }
+}
+
+#[must_be_async]
+impl BaseHttpClient for HyperClient {
+  type Error = HttpError<HyperClientError>;
+
+  async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Self::Error> {
+    let timeout = request.timeout.or(self.timeout);
+    with_timeout(timeout, async {
+      read_response(self.exchange(request).await?).await
+    })
+    .await
+  }
+
+  async fn send_streaming(&self, request: &HttpRequest) -> Result<StreamingResponse, Self::Error> {
+    // the body is read by the caller, after the deadline of the request
+    let timeout = request.timeout.or(self.timeout);
+    let response = with_timeout(timeout, self.exchange(request)).await?;
+    let status = response.status().as_u16();
+    let headers = convert_headers(response.headers());
+    let body = response
+      .into_body()
+      .into_data_stream()
+      .map(|chunk| chunk.map(|chunk| chunk.to_vec()).map_err(io::Error::other));
+    Ok(StreamingResponse::new(status, headers, Box::pin(body)))
+  }
+}
+
+impl CustomCertHttpClient for HyperClient {
+  fn with_custom_cert<CA, CLIENT, KEY>(ca: &CA, client: &CLIENT, key: &KEY) -> Self
+  where
+    CA: AsRef<[u8]>,
+    CLIENT: AsRef<[u8]>,
+    KEY: AsRef<[u8]>,
+  {
+    let config = HttpClientConfig::new().with_custom_cert(ca, client, key);
+    Self::from_config(&config).expect("Failed to initialize TLS")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::net::{IpAddr, Ipv4Addr};
+
+  use serde_json::json;
+
+  use super::{HyperClient, HyperClientError};
+  use crate::common::{CustomCertHttpClient, Headers, HttpError, Query};
+  use crate::config::{ConfigError, FromConfig, HttpClientConfig, Proxy};
+  use crate::nonblocking::BaseHttpClient;
+  use crate::testing::serve_once;
+
+  #[tokio::test]
+  async fn sends_json_body_and_headers() {
+    let (url, server) = serve_once("HTTP/1.1 200 OK\r\ncontent-length: 2\r\nX-Test: yes\r\n\r\nok");
+    let headers = Headers::from([("User-Agent".to_string(), "gf-auth".to_string())]);
+
+    let response = HyperClient::default()
+      .post(&url, Some(&headers), &json!({ "key": "value" }))
+      .await
+      .unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body(), "ok");
+    assert_eq!(response.headers().get("X-Test"), Some("yes"));
+
+    let request = server.join().unwrap();
+    assert!(request.starts_with("POST / HTTP/1.1\r\n"));
+    assert!(request.contains("User-Agent: gf-auth\r\n"));
+    assert!(request.contains("Content-Type: application/json\r\n"));
+    assert!(request.ends_with(r#"{"key":"value"}"#));
+  }
+
+  #[tokio::test]
+  async fn appends_query_and_reports_status_errors() {
+    let (url, server) = serve_once("HTTP/1.1 404 Not Found\r\ncontent-length: 7\r\n\r\nmissing");
+
+    let result = HyperClient::default()
+      .get(&url, None, &Query::from([("page", "1")]))
+      .await;
+
+    assert!(matches!(
+      result,
+      Err(HttpError::Status { status: 404, body, .. }) if body == b"missing"
+    ));
+    assert!(server
+      .join()
+      .unwrap()
+      .starts_with("GET /?page=1 HTTP/1.1\r\n"));
+  }
+
+  #[tokio::test]
+  async fn tunnels_through_configured_proxy() {
+    let (url, proxy) =
+      serve_once("HTTP/1.1 407 Proxy Authentication Required\r\ncontent-length: 0\r\n\r\n");
+    let config = HttpClientConfig::new()
+      .with_proxy(Proxy::new(&url).unwrap().with_auth("user", "secret"))
+      .with_local_address(IpAddr::V4(Ipv4Addr::LOCALHOST));
+    let client = HyperClient::from_config(&config).unwrap();
+
+    let result = client
+      .get("http://gameforge.invalid/api", None, &Query::new())
+      .await;
+    assert!(matches!(
+      result,
+      Err(HttpError::Client(HyperClientError::Connection(_)))
+    ));
+
+    let request = proxy.join().unwrap().to_lowercase();
+    assert!(request.starts_with("connect gameforge.invalid:80 http/1.1\r\n"));
+    assert!(request.contains("proxy-authorization: basic dxnlcjpzzwnyzxq=\r\n"));
+
+    let https = HttpClientConfig::new().with_proxy(Proxy::new("https://proxy.local").unwrap());
+    assert!(matches!(
+      HyperClient::from_config(&https),
+      Err(ConfigError::Unsupported(_))
+    ));
+  }
+
+  #[tokio::test]
+  async fn create_client_with_custom_cert_rustls_tls() -> Result<(), HttpError<HyperClientError>> {
+    println!("Testing hyper with rustls-tls");
+    let client = HyperClient::with_custom_cert(
+      include_bytes!("../../../../resources/ca.pem"),
+      include_bytes!("../../../../resources/client.pem"),
+      include_bytes!("../../../../resources/key.pem"),
+    );
+
+    let response = client
+      .get(
+        "https://events2.gameforge.com/",
+        Default::default(),
+        &Default::default(),
+      )
+      .await?;
+
+    assert_eq!(response.status(), 200);
+    Ok(())
+  }
+
+  #[tokio::test]
+  async fn times_out_requests() {
+    use std::time::Duration;
+
+    use crate::common::{HttpRequest, Method};
+    use crate::testing::serve_never;
+
+    let (url, _listener) = serve_never();
+    let request = HttpRequest::new(Method::Get, url).with_timeout(Duration::from_millis(100));
+
+    let result = HyperClient::default().send(&request).await;
+    assert!(matches!(result, Err(HttpError::Timeout)));
+  }
+
+  #[tokio::test]
+  async fn sends_http2_with_prior_knowledge() {
+    use crate::config::{ConnectionConfig, HttpVersion};
+    use crate::testing::accept_preface;
+
+    let (url, server) = accept_preface();
+    let connection = ConnectionConfig::new().with_http_version(HttpVersion::Http2PriorKnowledge);
+    let config = HttpClientConfig::new().with_connection(connection);
+    let client = HyperClient::from_config(&config).unwrap();
+
+    // the server closes the connection once the preface is read
+    let _ = client.get(&url, None, &Query::new()).await;
+    assert_eq!(server.join().unwrap(), b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n");
+  }
+
+  #[tokio::test]
+  async fn streams_response_bodies() {
+    use futures_lite::StreamExt;
+
+    use crate::common::{HttpRequest, Method};
+
+    let (url, server) = serve_once(b"HTTP/1.1 200 OK\r\ncontent-length: 3\r\n\r\n\x89P\x00");
+    let request = HttpRequest::new(Method::Get, url);
+    let response = HyperClient::default()
+      .send_streaming(&request)
+      .await
+      .unwrap();
+    assert_eq!(response.status(), 200);
+    let chunks = response
+      .into_body()
+      .try_collect::<_, _, Vec<_>>()
+      .await
+      .unwrap();
+    server.join().unwrap();
+    assert_eq!(chunks.concat(), b"\x89P\x00");
+  }
+
+  #[tokio::test]
+  #[cfg(feature = "pinning")]
+  async fn rejects_servers_without_pinned_keys() {
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+
+    use crate::pinning::Pin;
+    use crate::testing::serve_tls;
+
+    let mut ca_params = CertificateParams::new(Vec::new()).unwrap();
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca_key = KeyPair::generate().unwrap();
+    let ca = ca_params.clone().self_signed(&ca_key).unwrap();
+    let intermediate_key = KeyPair::generate().unwrap();
+    let intermediate = ca_params
+      .signed_by(&intermediate_key, &ca, &ca_key)
+      .unwrap();
+    let key = KeyPair::generate().unwrap();
+    let leaf = CertificateParams::new(vec!["127.0.0.1".to_string()])
+      .unwrap()
+      .signed_by(&key, &intermediate, &intermediate_key)
+      .unwrap();
+    // sent along the chain without being part of it
+    let unrelated_key = KeyPair::generate().unwrap();
+    let unrelated = CertificateParams::new(vec!["127.0.0.1".to_string()])
+      .unwrap()
+      .self_signed(&unrelated_key)
+      .unwrap();
+
+    let (url, server) = serve_tls(&[&leaf, &intermediate, &unrelated], &key, 5);
+
+    let pin = |cert: &rcgen::Certificate| Pin::from_certificate(cert.der()).unwrap();
+    let request = |pin: Pin| {
+      let config = HttpClientConfig::new()
+        .with_custom_cert(&ca.pem(), &leaf.pem(), &key.serialize_pem())
+        .with_pins("127.0.0.1", [pin]);
+      let url = url.clone();
+      async move {
+        HyperClient::from_config(&config)
+          .unwrap()
+          .get(&url, None, &Query::new())
+          .await
+      }
+    };
+
+    // the keys of the verified chain, up to the root, can be pinned
+    for pinned in [&leaf, &intermediate, &ca] {
+      assert_eq!(request(pin(pinned)).await.unwrap().status(), 200);
+    }
+    // the other certificates sent by the server aren't trusted
+    for pinned in [pin(&unrelated), Pin::from_sha256([0; 32])] {
+      let err = request(pinned).await.unwrap_err();
+      assert_eq!(err.pin_mismatch().unwrap().host, "127.0.0.1");
+    }
+    server.join().unwrap();
+  }
+}
@@ -0,0 +1,39 @@
+use crate::common::Headers;
+#[cfg(feature = "cookies")]
+use hyper::header::SET_COOKIE;
+use hyper::header::{HeaderMap, HeaderName, HeaderValue};
+
+pub(crate) fn convert_headers(raw: &HeaderMap) -> Headers {
+  raw
+    .iter()
+    .filter_map(|(key, value)| {
+      let value = match value.to_str() {
+        Ok(value) => value.to_string(),
+        Err(_) => {
+          log::error!("malformed header received: {key}");
+          return None;
+        }
+      };
+
+      Some((key.to_string().to_lowercase(), value))
+    })
+    .collect()
+}
+
+/// Converts the headers of a request.
+pub(crate) fn header_map(headers: &Headers) -> Result<HeaderMap, hyper::http::Error> {
+  let mut map = HeaderMap::with_capacity(headers.len());
+  for (name, value) in headers {
+    map.append(HeaderName::try_from(name)?, HeaderValue::try_from(value)?);
+  }
+  Ok(map)
+}
+
+/// Values of the `Set-Cookie` headers, which can be sent more than once.
+#[cfg(feature = "cookies")]
+pub(crate) fn set_cookies(raw: &HeaderMap) -> impl Iterator<Item = &str> {
+  raw
+    .get_all(SET_COOKIE)
+    .iter()
+    .filter_map(|value| value.to_str().ok())
+}
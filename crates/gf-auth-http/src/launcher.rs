@@ -0,0 +1,174 @@
+use std::ops::ControlFlow;
+
+#[cfg(feature = "identity")]
+use gf_auth_model::Identity;
+
+use crate::common::{Headers, HttpResponse};
+use crate::middleware::{Middleware, RequestParts};
+
+/// Origin of the pages displayed by the launcher.
+pub const LAUNCHER_ORIGIN: &str = "spark://www.gameforge.com";
+
+/// Header carrying the id of the launcher installation.
+pub const INSTALLATION_ID_HEADER: &str = "TNT-Installation-Id";
+
+/// The headers the Gameforge launcher sends with its requests, see
+/// [`LauncherProfile::headers`] for their order.
+///
+/// Added to a [`MiddlewareClient`](crate::MiddlewareClient), it puts these headers first
+/// on every request, followed by the other headers of the request in their order. A
+/// header set by the request replaces the one of the profile at the same position.
+///
+/// ureq sends the names with the casing of the profile. The reqwest and hyper clients
+/// send them in title case over HTTP/1, e.g. `Tnt-Installation-Id`, and in lowercase
+/// over HTTP/2 like every HTTP/2 client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LauncherProfile {
+  pub user_agent: String,
+  pub installation_id: String,
+  /// Value of the `Accept-Language` header.
+  pub accept_language: String,
+}
+
+impl LauncherProfile {
+  pub fn new(user_agent: impl Into<String>, installation_id: impl Into<String>) -> Self {
+    LauncherProfile {
+      user_agent: user_agent.into(),
+      installation_id: installation_id.into(),
+      accept_language: "en-US,en;q=0.9".to_string(),
+    }
+  }
+
+  /// Profile of the launcher the identity was created with, using the user agent and the
+  /// languages of its fingerprint.
+  #[cfg(feature = "identity")]
+  pub fn from_identity(identity: &Identity) -> Self {
+    let fingerprint = &identity.fingerprint;
+    LauncherProfile::new(
+      fingerprint.user_agent(),
+      identity.installation_id.to_string(),
+    )
+    .with_accept_language(accept_language(fingerprint.languages()))
+  }
+
+  pub fn with_accept_language(mut self, accept_language: impl Into<String>) -> Self {
+    self.accept_language = accept_language.into();
+    self
+  }
+
+  /// The headers in the order the launcher sends them: `User-Agent`,
+  /// `TNT-Installation-Id`, `Origin`, `Accept` and `Accept-Language`.
+  pub fn headers(&self) -> Headers {
+    Headers::from([
+      ("User-Agent", self.user_agent.as_str()),
+      (INSTALLATION_ID_HEADER, self.installation_id.as_str()),
+      ("Origin", LAUNCHER_ORIGIN),
+      ("Accept", "*/*"),
+      ("Accept-Language", self.accept_language.as_str()),
+    ])
+  }
+}
+
+impl Middleware for LauncherProfile {
+  fn on_request(&self, request: &mut RequestParts) -> ControlFlow<HttpResponse> {
+    let profile = self.headers();
+    let mut headers = Headers::new();
+    for (name, value) in profile.iter() {
+      let mut values = request.headers.get_all(name).peekable();
+      if values.peek().is_none() {
+        headers.append(name, value);
+      }
+      for value in values {
+        headers.append(name, value);
+      }
+    }
+    for (name, value) in request.headers.iter() {
+      if !profile.contains_key(name) {
+        headers.append(name, value);
+      }
+    }
+
+    request.headers = headers;
+    ControlFlow::Continue(())
+  }
+}
+
+/// Turns a list of languages into an `Accept-Language` value with decreasing weights, the
+/// way browsers do.
+#[cfg_attr(not(feature = "identity"), allow(dead_code))]
+fn accept_language(languages: &str) -> String {
+  languages
+    .split(',')
+    .map(str::trim)
+    .filter(|language| !language.is_empty())
+    .enumerate()
+    .map(|(index, language)| match index {
+      0 => language.to_string(),
+      _ => format!("{language};q={:.1}", (10 - index.min(9)) as f32 / 10.0),
+    })
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+  use std::ops::ControlFlow;
+
+  use super::{accept_language, LauncherProfile};
+  use crate::common::{Headers, Method};
+  use crate::middleware::{Middleware, RequestParts};
+
+  #[test]
+  fn puts_launcher_headers_first() {
+    let profile = LauncherProfile::new("Launcher/1.0", "installation");
+    let mut request = RequestParts {
+      method: Method::Post,
+      url: "https://spark.gameforge.com/api/v1/auth/sessions".to_string(),
+      headers: Headers::from([
+        ("Content-Type", "application/json"),
+        ("accept-language", "de-DE"),
+        ("Authorization", "Bearer token"),
+      ]),
+    };
+
+    assert!(matches!(
+      profile.on_request(&mut request),
+      ControlFlow::Continue(())
+    ));
+    assert_eq!(
+      request.headers.iter().collect::<Vec<_>>(),
+      [
+        ("User-Agent", "Launcher/1.0"),
+        ("TNT-Installation-Id", "installation"),
+        ("Origin", "spark://www.gameforge.com"),
+        ("Accept", "*/*"),
+        ("Accept-Language", "de-DE"),
+        ("Content-Type", "application/json"),
+        ("Authorization", "Bearer token"),
+      ]
+    );
+  }
+
+  #[test]
+  fn weighs_languages() {
+    assert_eq!(accept_language("en-US,pl, en"), "en-US,pl;q=0.9,en;q=0.8");
+    assert_eq!(accept_language(""), "");
+  }
+
+  #[test]
+  #[cfg(feature = "identity")]
+  fn derives_profile_from_identity() {
+    let file = std::fs::read_to_string("../../resources/identity/identity_full.json").unwrap();
+    let identity = serde_json::from_str::<gf_auth_model::Identity>(&file).unwrap();
+
+    let profile = LauncherProfile::from_identity(&identity);
+    assert_eq!(
+      profile.installation_id,
+      "fe2495d2-0d0c-4dde-8525-076ff5570a59"
+    );
+    assert!(profile
+      .user_agent
+      .starts_with("Mozilla/5.0 (X11; Linux x86_64)"));
+    assert_eq!(profile.accept_language, "en-US,pl;q=0.9,en;q=0.8");
+  }
+}
@@ -1,36 +1,147 @@
+//! HTTP compatibility layer for gf-auth.
+//!
+//! The clients come in two flavours, which can be enabled at the same time:
+//!
+//! - [`nonblocking`], the `async` one, with the `client-reqwest`, `client-hyper` or
+//!   `client-async-io` features. Only the `client-async-io` backend doesn't need tokio.
+//! - [`blocking`], the synchronous one, with the `client-ureq` or `client-reqwest-blocking`
+//!   features.
+//!
+//! Each flavour has its own [`BaseHttpClient`](nonblocking::BaseHttpClient) trait, which
+//! is implemented by the backends of that flavour and by all the client wrappers, such
+//! as [`RetryClient`] or [`RateLimitClient`]. When only one flavour is enabled, its
+//! `BaseHttpClient`, `HttpClient` and `ClientError` are also available at the crate root.
+
+/// Compiles the given items once for every enabled flavour, in the `nonblocking` and
+/// `blocking` submodules of the current module.
+///
+/// The items are written as `async` code and annotated with `#[maybe_async]`, which
+/// either keeps them `async` or converts them to blocking code. Tests use `#[async_test]`,
+/// which is `#[tokio::test]` or `#[test]` respectively.
+macro_rules! flavoured {
+  ($($item:tt)*) => {
+    #[cfg(feature = "__async")]
+    pub(crate) mod nonblocking {
+      #[allow(unused_imports)]
+      use super::*;
+      #[allow(unused_imports)]
+      use crate::nonblocking::prelude::*;
+
+      $($item)*
+    }
+
+    #[cfg(feature = "__sync")]
+    pub(crate) mod blocking {
+      #[allow(unused_imports)]
+      use super::*;
+      #[allow(unused_imports)]
+      use crate::blocking::prelude::*;
+
+      $($item)*
+    }
+  };
+}
+
+#[cfg(feature = "__sync")]
+pub mod blocking;
+#[cfg(feature = "cassette")]
+mod cassette;
 mod common;
-#[cfg(feature = "client-reqwest")]
-#[cfg(not(all(feature = "client-reqwest", feature = "client-ureq")))]
+mod config;
+#[cfg(feature = "cookies")]
+mod cookies;
+#[cfg(any(feature = "client-hyper", feature = "client-async-io"))]
+mod hyper;
+mod launcher;
+mod logging;
+#[cfg(feature = "metrics")]
+mod metric;
+mod middleware;
+#[cfg(feature = "test-util")]
+mod mock;
+#[cfg(feature = "__async")]
+pub mod nonblocking;
+#[cfg(feature = "pinning")]
+mod pinning;
+mod pool;
+mod ratelimit;
+#[cfg(all(feature = "metrics", any(test, feature = "test-util")))]
+mod recorder;
+#[cfg(any(feature = "client-reqwest", feature = "client-reqwest-blocking"))]
 mod reqwest;
-
+mod retry;
+#[cfg(test)]
+mod testing;
+#[cfg(feature = "tracing")]
+mod trace;
 #[cfg(feature = "client-ureq")]
-#[cfg(not(all(feature = "client-reqwest", feature = "client-ureq")))]
 mod ureq;
 
-#[cfg(any(feature = "client-reqwest", feature = "client-ureq"))]
-#[cfg(not(all(feature = "client-reqwest", feature = "client-ureq")))]
-pub use crate::common::{BaseHttpClient, CustomCertHttpClient, Form, Headers, HttpError, Query};
+#[cfg(feature = "cassette")]
+pub use crate::cassette::{
+  Cassette, CassetteBody, CassetteError, CassetteRequest, CassetteResponse, CassetteResponseBody,
+  Interaction, MatchRules, RecordingClient, ReplayClient,
+};
+pub use crate::common::{
+  Body, CustomCertHttpClient, Form, Headers, HttpError, HttpRequest, HttpResponse, Method,
+  Multipart, Part, Query,
+};
+pub use crate::config::{
+  ConfigError, ConnectionConfig, CustomCert, FromConfig, HttpClientConfig, HttpVersion, Proxy,
+};
+#[cfg(feature = "cookies")]
+pub use crate::cookies::CookieJar;
+pub use crate::launcher::{LauncherProfile, INSTALLATION_ID_HEADER, LAUNCHER_ORIGIN};
+pub use crate::logging::RequestLogger;
+#[cfg(feature = "metrics")]
+pub use crate::metric::{MetricsClient, REQUESTS_TOTAL, REQUEST_DURATION_SECONDS};
+pub use crate::middleware::{
+  DefaultHeaders, Middleware, MiddlewareClient, RequestId, RequestParts,
+};
+#[cfg(feature = "test-util")]
+pub use crate::mock::{
+  Expectation, ExpectationHandle, MockError, MockHttpClient, RecordedBody, RecordedRequest,
+};
+#[cfg(feature = "pinning")]
+pub use crate::pinning::{Pin, PinMismatch};
+pub use crate::pool::{AccountRoute, ClientPool};
+pub use crate::ratelimit::{Quota, RateLimitClient, RateLimitMode, RateLimiter};
+#[cfg(all(feature = "metrics", feature = "test-util"))]
+pub use crate::recorder::MetricsRecorder;
+pub use crate::retry::{RetryClient, RetryPolicy};
+#[cfg(feature = "tracing")]
+pub use crate::trace::TracingClient;
 
+#[cfg(feature = "client-async-io")]
+pub use crate::hyper::AsyncIoClient;
+#[cfg(feature = "client-hyper")]
+pub use crate::hyper::HyperClient;
+#[cfg(any(feature = "client-hyper", feature = "client-async-io"))]
+pub use crate::hyper::HyperClientError;
+#[cfg(feature = "client-reqwest-blocking")]
+pub use crate::reqwest::ReqwestBlockingClient;
 #[cfg(feature = "client-reqwest")]
-#[cfg(not(all(feature = "client-reqwest", feature = "client-ureq")))]
-pub use crate::reqwest::{ReqwestClient as HttpClient, ReqwestClientError as ClientError};
-
+pub use crate::reqwest::ReqwestClient;
+#[cfg(any(feature = "client-reqwest", feature = "client-reqwest-blocking"))]
+pub use crate::reqwest::ReqwestClientError;
 #[cfg(feature = "client-ureq")]
-#[cfg(not(all(feature = "client-reqwest", feature = "client-ureq")))]
-pub use self::ureq::{UreqClient as HttpClient, UreqClientError as ClientError};
+pub use crate::ureq::{UreqClient, UreqClientError};
 
-#[cfg(all(feature = "client-reqwest", feature = "client-ureq"))]
-compile_error!(
-  "`client-reqwest` and `client-ureq` features cannot both be enabled at \
-    the same time, if you want to use `client-ureq` you need to set \
-    `default-features = false`"
-);
+#[cfg(all(feature = "__async", not(feature = "__sync")))]
+pub use crate::nonblocking::{BaseHttpClient, ClientError, HttpClient};
+
+#[cfg(all(feature = "__sync", not(feature = "__async")))]
+pub use crate::blocking::{BaseHttpClient, ClientError, HttpClient};
 
-#[cfg(not(any(feature = "client-reqwest", feature = "client-ureq")))]
+#[cfg(not(any(
+  feature = "client-reqwest",
+  feature = "client-reqwest-blocking",
+  feature = "client-hyper",
+  feature = "client-async-io",
+  feature = "client-ureq"
+)))]
 compile_error!(
   "You have to enable at least one of the available clients with the \
-    `client-reqwest` or `client-ureq` features."
+    `client-reqwest`, `client-reqwest-blocking`, `client-hyper`, `client-async-io` or \
+    `client-ureq` features."
 );
-
-#[cfg(all(feature = "__async", feature = "__sync"))]
-compile_error!("Async and Sync versions of HttpClient cannot both be enabled at the same time.");
@@ -0,0 +1,310 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use log::Level;
+use serde_json::Value;
+use url::Url;
+
+use crate::common::{Body, Headers, HttpRequest};
+
+pub(crate) const REDACTED: &str = "<redacted>";
+
+const DEFAULT_REDACTED_HEADERS: &[&str] = &[
+  "authorization",
+  "proxy-authorization",
+  "cookie",
+  "set-cookie",
+];
+
+const DEFAULT_REDACTED_FIELDS: &[&str] = &["password", "blackbox"];
+
+/// Logs the requests made by the HTTP clients, with secrets such as credentials,
+/// cookies and blackboxes replaced by a placeholder.
+///
+/// Header names and payload fields are matched case-insensitively, payload fields are
+/// redacted in JSON bodies (at any depth), forms and URL query strings.
+#[derive(Debug, Clone)]
+pub struct RequestLogger {
+  level: Level,
+  redacted_headers: Vec<String>,
+  redacted_fields: Vec<String>,
+}
+
+impl Default for RequestLogger {
+  fn default() -> Self {
+    RequestLogger {
+      level: Level::Info,
+      redacted_headers: DEFAULT_REDACTED_HEADERS
+        .iter()
+        .map(|name| name.to_string())
+        .collect(),
+      redacted_fields: DEFAULT_REDACTED_FIELDS
+        .iter()
+        .map(|name| name.to_string())
+        .collect(),
+    }
+  }
+}
+
+impl RequestLogger {
+  pub fn with_level(mut self, level: Level) -> Self {
+    self.level = level;
+    self
+  }
+
+  pub fn with_redacted_header(mut self, name: impl Into<String>) -> Self {
+    self.redacted_headers.push(name.into());
+    self
+  }
+
+  pub fn with_redacted_field(mut self, name: impl Into<String>) -> Self {
+    self.redacted_fields.push(name.into());
+    self
+  }
+
+  /// Removes all redaction rules, including the default ones.
+  pub fn without_redaction(mut self) -> Self {
+    self.redacted_headers.clear();
+    self.redacted_fields.clear();
+    self
+  }
+
+  pub fn level(&self) -> Level {
+    self.level
+  }
+
+  fn is_redacted_header(&self, name: &str) -> bool {
+    self
+      .redacted_headers
+      .iter()
+      .any(|header| header.eq_ignore_ascii_case(name))
+  }
+
+  pub(crate) fn is_redacted_field(&self, name: &str) -> bool {
+    self
+      .redacted_fields
+      .iter()
+      .any(|field| field.eq_ignore_ascii_case(name))
+  }
+
+  pub(crate) fn redact_headers<'a, I>(&self, headers: I) -> Headers
+  where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+  {
+    headers
+      .into_iter()
+      .map(|(name, value)| {
+        let value = if self.is_redacted_header(name) {
+          REDACTED.to_string()
+        } else {
+          value.to_string()
+        };
+        (name.to_string(), value)
+      })
+      .collect()
+  }
+
+  pub(crate) fn redact_url(&self, url: &str) -> String {
+    let mut parsed = match Url::parse(url) {
+      Ok(parsed) => parsed,
+      Err(_) => return url.to_string(),
+    };
+
+    if parsed.query().is_none() {
+      return url.to_string();
+    }
+
+    let pairs = parsed
+      .query_pairs()
+      .map(|(key, value)| {
+        let value = if self.is_redacted_field(&key) {
+          REDACTED.into()
+        } else {
+          value
+        };
+        (key.into_owned(), value.into_owned())
+      })
+      .collect::<Vec<_>>();
+
+    parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    parsed.to_string()
+  }
+
+  pub(crate) fn redact_json(&self, value: &Value) -> Value {
+    match value {
+      Value::Object(map) => Value::Object(
+        map
+          .iter()
+          .map(|(key, value)| {
+            let value = if self.is_redacted_field(key) {
+              Value::String(REDACTED.to_string())
+            } else {
+              self.redact_json(value)
+            };
+            (key.clone(), value)
+          })
+          .collect(),
+      ),
+      Value::Array(values) => Value::Array(values.iter().map(|v| self.redact_json(v)).collect()),
+      value => value.clone(),
+    }
+  }
+
+  pub(crate) fn redact_pairs<'a, I>(&self, pairs: I) -> BTreeMap<String, String>
+  where
+    I: IntoIterator<Item = &'a (String, String)>,
+  {
+    pairs
+      .into_iter()
+      .map(|(key, value)| {
+        let value = if self.is_redacted_field(key) {
+          REDACTED
+        } else {
+          value
+        };
+        (key.clone(), value.to_string())
+      })
+      .collect()
+  }
+
+  /// The body as written to the logs. Raw bytes and files are only described, as they
+  /// can be large or binary.
+  pub(crate) fn redact_body(&self, body: &Body) -> String {
+    match body {
+      Body::Empty => String::new(),
+      Body::Json(value) => self.redact_json(value).to_string(),
+      Body::Form(pairs) => format!("{:?}", self.redact_pairs(pairs)),
+      Body::Bytes(bytes) => format!("<{} bytes>", bytes.len()),
+      Body::Multipart(multipart) => {
+        let parts = multipart
+          .parts
+          .iter()
+          .map(|part| match &part.filename {
+            Some(filename) => format!("{}: <file {filename}>", part.name),
+            None if self.is_redacted_field(&part.name) => format!("{}: {REDACTED}", part.name),
+            None => format!("{}: {}", part.name, String::from_utf8_lossy(&part.data)),
+          })
+          .collect::<Vec<_>>();
+        format!("<multipart {{{}}}>", parts.join(", "))
+      }
+    }
+  }
+
+  pub(crate) fn log_request(&self, request: &HttpRequest) {
+    if !log::log_enabled!(self.level) {
+      return;
+    }
+
+    let method = request.method.as_str();
+    let url = self.redact_url(&request.full_url());
+    let headers = self.redact_headers(&request.headers);
+    let body = self.redact_body(&request.body);
+    log::log!(
+      self.level,
+      method,
+      url = url.as_str(),
+      headers:? = headers,
+      body = body.as_str();
+      "Making request {method} {url}"
+    );
+  }
+
+  pub(crate) fn log_response(&self, method: &str, url: &str, status: u16, elapsed: Duration) {
+    if !log::log_enabled!(self.level) {
+      return;
+    }
+
+    let url = self.redact_url(url);
+    log::log!(
+      self.level,
+      method,
+      url = url.as_str(),
+      status,
+      elapsed:? = elapsed;
+      "Received response {status} for {method} {url} in {elapsed:?}"
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use crate::common::{Body, Headers, Multipart};
+  use crate::logging::RequestLogger;
+
+  #[test]
+  fn redacts_sensitive_headers() {
+    let mut headers = Headers::new();
+    headers.insert("Authorization".to_string(), "Bearer secret".to_string());
+    headers.insert("cookie".to_string(), "session=secret".to_string());
+    headers.insert("user-agent".to_string(), "launcher".to_string());
+    headers.append("accept", "text/html");
+    headers.append("accept", "application/json");
+
+    let redacted = RequestLogger::default().redact_headers(&headers);
+    assert_eq!(redacted.get("Authorization"), Some("<redacted>"));
+    assert_eq!(redacted.get("cookie"), Some("<redacted>"));
+    assert_eq!(redacted.get("user-agent"), Some("launcher"));
+    assert_eq!(
+      redacted.get_all("accept").collect::<Vec<_>>(),
+      ["text/html", "application/json"]
+    );
+  }
+
+  #[test]
+  fn redacts_json_fields_at_any_depth() {
+    let body = json!({
+      "email": "user@example.com",
+      "Password": "secret",
+      "nested": [{ "blackbox": "tra:secret" }]
+    });
+
+    let redacted = RequestLogger::default().redact_json(&body);
+    assert_eq!(
+      redacted,
+      json!({
+        "email": "user@example.com",
+        "Password": "<redacted>",
+        "nested": [{ "blackbox": "<redacted>" }]
+      })
+    );
+  }
+
+  #[test]
+  fn redacts_forms_and_query_strings() {
+    let logger = RequestLogger::default().with_redacted_field("token");
+
+    let form = Body::Form(vec![
+      ("user".to_string(), "name".to_string()),
+      ("password".to_string(), "secret".to_string()),
+    ]);
+    assert_eq!(
+      logger.redact_body(&form),
+      r#"{"password": "<redacted>", "user": "name"}"#
+    );
+
+    let multipart = Multipart::new().with_text("token", "secret").with_file(
+      "avatar",
+      "me.png",
+      "image/png",
+      vec![0; 16],
+    );
+    assert_eq!(
+      logger.redact_body(&Body::Multipart(multipart)),
+      "<multipart {token: <redacted>, avatar: <file me.png>}>"
+    );
+
+    assert_eq!(
+      logger.redact_url("https://spark.gameforge.com/api?token=secret&locale=en"),
+      "https://spark.gameforge.com/api?token=%3Credacted%3E&locale=en"
+    );
+  }
+
+  #[test]
+  fn redaction_can_be_disabled() {
+    let logger = RequestLogger::default().without_redaction();
+    let body = json!({ "password": "secret" });
+    assert_eq!(logger.redact_json(&body), body);
+  }
+}
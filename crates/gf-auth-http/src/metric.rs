@@ -0,0 +1,178 @@
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Instant;
+
+use metrics::{counter, describe_counter, describe_histogram, histogram, Unit};
+use url::Url;
+
+use crate::common::{HttpError, HttpRequest, HttpResponse, Method};
+
+/// Counter of the requests made through a [`MetricsClient`].
+pub const REQUESTS_TOTAL: &str = "gf_auth_http_requests_total";
+/// Histogram of the durations of the requests made through a [`MetricsClient`], in seconds.
+pub const REQUEST_DURATION_SECONDS: &str = "gf_auth_http_request_duration_seconds";
+
+/// A `BaseHttpClient` wrapper which records every request with the `metrics` facade.
+///
+/// Both [`REQUESTS_TOTAL`] and [`REQUEST_DURATION_SECONDS`] are labelled with the
+/// `method` and `host` of the request, the template of its `path` and the `status` class
+/// of the response (`2xx`, `4xx`, ...), or `error` when no response was received.
+///
+/// The ids in the path, numbers, UUIDs and long hexadecimal strings, are replaced with
+/// `{id}` to keep the number of label values bounded, templates added with
+/// [`MetricsClient::with_path_template`] take precedence.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsClient<C> {
+  inner: C,
+  templates: Arc<Vec<String>>,
+}
+
+impl<C> MetricsClient<C> {
+  pub fn new(inner: C) -> Self {
+    MetricsClient {
+      inner,
+      templates: Arc::default(),
+    }
+  }
+
+  /// Labels the paths matching the template with the template itself, its segments in
+  /// braces matching any segment, e.g. `/challenge/{id}/{locale}`.
+  pub fn with_path_template(mut self, template: impl Into<String>) -> Self {
+    Arc::make_mut(&mut self.templates).push(template.into());
+    self
+  }
+
+  pub fn inner(&self) -> &C {
+    &self.inner
+  }
+
+  /// Registers the units and descriptions of the metrics with the installed recorder.
+  pub fn describe() {
+    describe_counter!(REQUESTS_TOTAL, Unit::Count, "HTTP requests made");
+    describe_histogram!(
+      REQUEST_DURATION_SECONDS,
+      Unit::Seconds,
+      "Duration of the HTTP requests"
+    );
+  }
+
+  fn record<E: Error>(
+    &self,
+    method: Method,
+    url: &str,
+    result: &Result<HttpResponse, HttpError<E>>,
+    started: Instant,
+  ) {
+    let parsed = Url::parse(url).ok();
+    let host = parsed
+      .as_ref()
+      .and_then(Url::host_str)
+      .unwrap_or_default()
+      .to_string();
+    let path = path_template(parsed.as_ref().map_or("", Url::path), &self.templates);
+    let status = match result {
+      Ok(response) => status_class(response.status()),
+      Err(HttpError::Status { status, .. }) => status_class(*status),
+      Err(_) => "error".to_string(),
+    };
+
+    let labels = [
+      ("method", method.as_str().to_string()),
+      ("host", host),
+      ("path", path),
+      ("status", status),
+    ];
+    counter!(REQUESTS_TOTAL, &labels).increment(1);
+    histogram!(REQUEST_DURATION_SECONDS, &labels).record(started.elapsed().as_secs_f64());
+  }
+}
+
+fn status_class(status: u16) -> String {
+  format!("{}xx", status / 100)
+}
+
+/// The first template matching the path, or the path with its ids replaced by `{id}`.
+fn path_template(path: &str, templates: &[String]) -> String {
+  let segments = path.split('/').collect::<Vec<_>>();
+  let matching = templates.iter().find(|template| {
+    let template = template.split('/').collect::<Vec<_>>();
+    template.len() == segments.len()
+      && template.iter().zip(&segments).all(|(expected, segment)| {
+        expected == segment || (expected.starts_with('{') && expected.ends_with('}'))
+      })
+  });
+  if let Some(template) = matching {
+    return template.clone();
+  }
+
+  segments
+    .iter()
+    .map(|segment| match is_id(segment) {
+      true => "{id}",
+      false => segment,
+    })
+    .collect::<Vec<_>>()
+    .join("/")
+}
+
+fn is_id(segment: &str) -> bool {
+  let digits = !segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit());
+  let uuid = segment.len() == 36 && segment.chars().all(|c| c.is_ascii_hexdigit() || c == '-');
+  let hex = segment.len() >= 16 && segment.chars().all(|c| c.is_ascii_hexdigit());
+  digits || uuid || hex
+}
+
+flavoured! {
+  #[maybe_async]
+  impl<C, E> BaseHttpClient for MetricsClient<C>
+  where
+    C: BaseHttpClient<Error = HttpError<E>> + Sync,
+    E: Error + Send,
+  {
+    type Error = C::Error;
+
+    async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Self::Error> {
+      let started = Instant::now();
+      let result = self.inner.send(request).await;
+      self.record(request.method, &request.url, &result, started);
+      result
+    }
+  }
+
+  #[cfg(test)]
+  mod tests {
+    use super::*;
+    use crate::common::Query;
+    use crate::metric::{MetricsClient, REQUEST_DURATION_SECONDS};
+    use crate::recorder::MetricsRecorder;
+    use crate::testing::ScriptedClient;
+
+    #[maybe_async]
+    #[async_test]
+    async fn records_requests_by_endpoint() {
+      let recorder = MetricsRecorder::default();
+      let _guard = metrics::set_default_local_recorder(&recorder);
+
+      let client = MetricsClient::new(ScriptedClient::new(&[Ok(200), Err(503), Ok(200)]))
+        .with_path_template("/challenge/{id}/{locale}");
+      for url in [
+        "https://spark.gameforge.com/api/v1/auth/sessions",
+        "https://spark.gameforge.com/api/v1/user/accounts/1042/codes",
+        "https://image-drop-challenge.gameforge.com/challenge/6c1bd0a4/en-GB",
+      ] {
+        let _ = client.get(url, None, &Query::new()).await;
+      }
+
+      let host = "gf_auth_http_requests_total{method=GET,host";
+      assert_eq!(
+        recorder.counters(),
+        [
+          format!("{host}=image-drop-challenge.gameforge.com,path=/challenge/{{id}}/{{locale}},status=2xx}} 1"),
+          format!("{host}=spark.gameforge.com,path=/api/v1/auth/sessions,status=2xx}} 1"),
+          format!("{host}=spark.gameforge.com,path=/api/v1/user/accounts/{{id}}/codes,status=5xx}} 1"),
+        ]
+      );
+      assert_eq!(recorder.histograms(REQUEST_DURATION_SECONDS), 3);
+    }
+  }
+}
@@ -0,0 +1,358 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::common::{Headers, HttpError, HttpRequest, HttpResponse, Method};
+
+/// The parts of an outgoing request which can be inspected and changed by a [`Middleware`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestParts {
+  pub method: Method,
+  pub url: String,
+  pub headers: Headers,
+}
+
+/// An interceptor which is called before and after every request made by a
+/// [`MiddlewareClient`].
+///
+/// Both hooks are synchronous, so a single implementation works for the async and the
+/// blocking clients alike.
+pub trait Middleware: fmt::Debug + Send + Sync {
+  /// Called before the request is sent, in the order the middlewares were added.
+  ///
+  /// Returning [`ControlFlow::Break`] skips the remaining middlewares and the inner client,
+  /// and the given response is returned to the caller instead.
+  fn on_request(&self, _request: &mut RequestParts) -> ControlFlow<HttpResponse> {
+    ControlFlow::Continue(())
+  }
+
+  /// Called after the response has been received, in reverse order.
+  ///
+  /// The responses of [`send_streaming`](crate::nonblocking::BaseHttpClient::send_streaming)
+  /// are given with an empty body, since it's only read by the caller afterwards.
+  fn on_response(
+    &self,
+    _request: &RequestParts,
+    _response: Result<&HttpResponse, &dyn Error>,
+    _elapsed: Duration,
+  ) {
+  }
+}
+
+impl RequestParts {
+  /// The request with the method, url and headers of the parts.
+  fn apply(&self, request: &HttpRequest) -> HttpRequest {
+    HttpRequest {
+      method: self.method,
+      url: self.url.clone(),
+      headers: self.headers.clone(),
+      ..request.clone()
+    }
+  }
+}
+
+/// A `BaseHttpClient` wrapper which runs every request through a chain of [`Middleware`]s.
+#[derive(Debug, Clone, Default)]
+pub struct MiddlewareClient<C> {
+  inner: C,
+  middlewares: Vec<Arc<dyn Middleware>>,
+}
+
+impl<C> MiddlewareClient<C> {
+  pub fn new(inner: C) -> Self {
+    MiddlewareClient {
+      inner,
+      middlewares: vec![],
+    }
+  }
+
+  pub fn with<M: Middleware + 'static>(mut self, middleware: M) -> Self {
+    self.middlewares.push(Arc::new(middleware));
+    self
+  }
+
+  pub fn inner(&self) -> &C {
+    &self.inner
+  }
+
+  /// Runs the request hooks, on a short-circuit the hooks of the middlewares which have
+  /// already seen the request are given the response.
+  fn before(&self, request: &HttpRequest) -> ControlFlow<HttpResponse, RequestParts> {
+    let mut request = RequestParts {
+      method: request.method,
+      url: request.url.clone(),
+      headers: request.headers.clone(),
+    };
+
+    for (index, middleware) in self.middlewares.iter().enumerate() {
+      if let ControlFlow::Break(response) = middleware.on_request(&mut request) {
+        for middleware in self.middlewares[..index].iter().rev() {
+          middleware.on_response(&request, Ok(&response), Duration::ZERO);
+        }
+        return ControlFlow::Break(response);
+      }
+    }
+
+    ControlFlow::Continue(request)
+  }
+
+  fn after<E: Error>(
+    &self,
+    request: &RequestParts,
+    response: &Result<HttpResponse, E>,
+    started: Instant,
+  ) {
+    let elapsed = started.elapsed();
+    for middleware in self.middlewares.iter().rev() {
+      let response = response.as_ref().map_err(|err| err as &dyn Error);
+      middleware.on_response(request, response, elapsed);
+    }
+  }
+}
+
+/// Adds the given headers to every request which doesn't already set them.
+#[derive(Debug, Clone, Default)]
+pub struct DefaultHeaders(pub Headers);
+
+impl Middleware for DefaultHeaders {
+  fn on_request(&self, request: &mut RequestParts) -> ControlFlow<HttpResponse> {
+    for (key, value) in self.0.iter() {
+      if !request.headers.contains_key(key) {
+        request.headers.append(key, value);
+      }
+    }
+
+    ControlFlow::Continue(())
+  }
+}
+
+/// Tags every request with a random UUID, unless the caller has already set one.
+#[derive(Debug, Clone)]
+pub struct RequestId {
+  header: String,
+}
+
+impl RequestId {
+  pub fn new(header: impl Into<String>) -> Self {
+    RequestId {
+      header: header.into(),
+    }
+  }
+}
+
+impl Default for RequestId {
+  fn default() -> Self {
+    RequestId::new("x-request-id")
+  }
+}
+
+impl Middleware for RequestId {
+  fn on_request(&self, request: &mut RequestParts) -> ControlFlow<HttpResponse> {
+    if !request.headers.contains_key(&self.header) {
+      request
+        .headers
+        .insert(self.header.clone(), uuid::Uuid::new_v4().to_string());
+    }
+
+    ControlFlow::Continue(())
+  }
+}
+
+flavoured! {
+  #[maybe_async]
+  impl<C, E> BaseHttpClient for MiddlewareClient<C>
+  where
+    C: BaseHttpClient<Error = HttpError<E>> + Sync,
+    E: Error + Send,
+  {
+    type Error = C::Error;
+
+    async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Self::Error> {
+      let parts = match self.before(request) {
+        ControlFlow::Continue(parts) => parts,
+        ControlFlow::Break(response) => return Ok(response),
+      };
+
+      let request = parts.apply(request);
+      let started = Instant::now();
+      let response = self.inner.send(&request).await;
+      self.after(&parts, &response, started);
+      response
+    }
+
+    async fn send_streaming(&self, request: &HttpRequest) -> Result<StreamingResponse, Self::Error> {
+      let parts = match self.before(request) {
+        ControlFlow::Continue(parts) => parts,
+        ControlFlow::Break(response) => return Ok(response.into()),
+      };
+
+      let request = parts.apply(request);
+      let started = Instant::now();
+      let response = self.inner.send_streaming(&request).await;
+      let head = response.as_ref().map(|response| {
+        HttpResponse::new(response.status(), response.headers().clone(), String::new())
+      });
+      self.after(&parts, &head, started);
+      response
+    }
+  }
+
+  #[cfg(test)]
+  mod tests {
+    use std::error::Error;
+    use std::ops::ControlFlow;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::*;
+    use crate::common::{Headers, HttpRequest, HttpResponse, Method, Query};
+    use crate::middleware::{
+      DefaultHeaders, Middleware, MiddlewareClient, RequestId, RequestParts,
+    };
+    use crate::testing::ScriptedClient;
+
+    const URL: &str = "https://spark.gameforge.com/api/v1/auth/sessions";
+
+    #[derive(Debug, Default)]
+    struct Recorder {
+      events: Arc<Mutex<Vec<String>>>,
+      name: &'static str,
+      short_circuit: bool,
+    }
+
+    impl Middleware for Recorder {
+      fn on_request(&self, request: &mut RequestParts) -> ControlFlow<HttpResponse> {
+        let event = format!("{} request {}", self.name, request.method);
+        self.events.lock().unwrap().push(event);
+
+        if self.short_circuit {
+          ControlFlow::Break(HttpResponse::new(204, Headers::new(), String::new()))
+        } else {
+          ControlFlow::Continue(())
+        }
+      }
+
+      fn on_response(
+        &self,
+        _: &RequestParts,
+        response: Result<&HttpResponse, &dyn Error>,
+        _: Duration,
+      ) {
+        let status = response.map(|response| response.status()).unwrap_or(0);
+        let event = format!("{} response {}", self.name, status);
+        self.events.lock().unwrap().push(event);
+      }
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn injects_headers() {
+      let mut defaults = Headers::new();
+      defaults.insert("accept-language".to_string(), "en-US".to_string());
+      defaults.insert("user-agent".to_string(), "default".to_string());
+
+      let client = MiddlewareClient::new(ScriptedClient::default())
+        .with(DefaultHeaders(defaults))
+        .with(RequestId::default());
+
+      let mut headers = Headers::new();
+      headers.insert("User-Agent".to_string(), "custom".to_string());
+      let result = client.get(URL, Some(&headers), &Query::new()).await;
+      assert!(result.is_ok());
+
+      let (url, headers) = client.inner().requests().remove(0);
+      assert_eq!(url, URL);
+      assert_eq!(headers.get("accept-language").unwrap(), "en-US");
+      assert_eq!(headers.get_all("user-agent").collect::<Vec<_>>(), ["custom"]);
+      assert!(headers.contains_key("x-request-id"));
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn runs_hooks_in_onion_order() {
+      let events = Arc::new(Mutex::new(vec![]));
+      let client = MiddlewareClient::new(ScriptedClient::new(&[Ok(200)]))
+        .with(Recorder {
+          events: events.clone(),
+          name: "outer",
+          ..Default::default()
+        })
+        .with(Recorder {
+          events: events.clone(),
+          name: "inner",
+          ..Default::default()
+        });
+
+      let result = client.options(URL, None).await;
+      assert!(result.is_ok());
+      assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+          "outer request OPTIONS",
+          "inner request OPTIONS",
+          "inner response 200",
+          "outer response 200",
+        ]
+      );
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn streams_through_the_inner_client() {
+      let events = Arc::new(Mutex::new(vec![]));
+      let client = MiddlewareClient::new(ScriptedClient::new(&[Ok(200)]))
+        .with(RequestId::default())
+        .with(Recorder {
+          events: events.clone(),
+          name: "outer",
+          ..Default::default()
+        });
+
+      let request = HttpRequest::new(Method::Get, URL);
+      let response = client.send_streaming(&request).await.unwrap();
+      assert_eq!(response.status(), 200);
+      assert_eq!(client.inner().streamed_calls(), 1);
+      assert!(client.inner().requests()[0].1.contains_key("x-request-id"));
+      assert_eq!(
+        *events.lock().unwrap(),
+        vec!["outer request GET", "outer response 200"]
+      );
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn short_circuits_the_chain() {
+      let events = Arc::new(Mutex::new(vec![]));
+      let client = MiddlewareClient::new(ScriptedClient::default())
+        .with(Recorder {
+          events: events.clone(),
+          name: "outer",
+          ..Default::default()
+        })
+        .with(Recorder {
+          events: events.clone(),
+          name: "cache",
+          short_circuit: true,
+        })
+        .with(Recorder {
+          events: events.clone(),
+          name: "inner",
+          ..Default::default()
+        });
+
+      let response = client.get(URL, None, &Query::new()).await.unwrap();
+      assert_eq!(response.status(), 204);
+      assert_eq!(client.inner().calls(), 0);
+      assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+          "outer request GET",
+          "cache request GET",
+          "outer response 204"
+        ]
+      );
+    }
+  }
+}
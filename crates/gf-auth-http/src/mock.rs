@@ -0,0 +1,565 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use serde_json::Value;
+use url::Url;
+
+use crate::common::{Body, Headers, HttpError, HttpRequest, HttpResponse, Method, Multipart};
+
+#[derive(thiserror::Error, Debug)]
+pub enum MockError {
+  #[error("no expectation matched request {method} {url}")]
+  Unmatched { method: Method, url: String },
+  #[error("transport: {0}")]
+  Transport(String),
+}
+
+/// The payload of a request received by the [`MockHttpClient`].
+///
+/// Requests without a body record their query parameters as the payload, the query of
+/// the other requests is kept in the recorded url.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordedBody {
+  None,
+  Query(BTreeMap<String, String>),
+  Json(Value),
+  Form(BTreeMap<String, String>),
+  Bytes(Vec<u8>),
+  Multipart(Multipart),
+}
+
+impl RecordedBody {
+  fn pairs(pairs: &[(String, String)]) -> BTreeMap<String, String> {
+    pairs.iter().cloned().collect()
+  }
+}
+
+/// A request received by the [`MockHttpClient`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedRequest {
+  pub method: Method,
+  pub url: String,
+  pub headers: Headers,
+  pub body: RecordedBody,
+  /// Query parameters of the url and of [`HttpRequest::query`], whatever the body.
+  pub query: Vec<(String, String)>,
+}
+
+impl RecordedRequest {
+  pub fn header(&self, name: &str) -> Option<&str> {
+    self.headers.get(name)
+  }
+}
+
+type BodyMatcher = Arc<dyn Fn(&RecordedBody) -> bool + Send + Sync>;
+
+#[derive(Debug, Clone)]
+enum MockResponse {
+  Response {
+    status: u16,
+    headers: Headers,
+    body: Vec<u8>,
+  },
+  Transport(String),
+}
+
+/// A request the [`MockHttpClient`] expects to receive, and the response it replies with.
+///
+/// URL patterns may contain `*` wildcards, which match any sequence of characters.
+#[derive(Clone)]
+pub struct Expectation {
+  method: Method,
+  url: String,
+  headers: Vec<(String, String)>,
+  query: Vec<(String, String)>,
+  body: Option<BodyMatcher>,
+  response: MockResponse,
+  times: Option<u32>,
+}
+
+impl Expectation {
+  pub fn new(method: Method, url: impl Into<String>) -> Self {
+    Expectation {
+      method,
+      url: url.into(),
+      headers: vec![],
+      query: vec![],
+      body: None,
+      response: MockResponse::Response {
+        status: 200,
+        headers: Headers::new(),
+        body: vec![],
+      },
+      times: None,
+    }
+  }
+
+  /// Requires the request to carry the given header, the name is matched case-insensitively.
+  pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+    self.headers.push((name.into(), value.into()));
+    self
+  }
+
+  /// Requires the request body to be equal to the given JSON value.
+  pub fn with_json_body(self, expected: Value) -> Self {
+    self.with_body(move |body| matches!(body, RecordedBody::Json(value) if *value == expected))
+  }
+
+  /// Requires the form body to contain the given field.
+  pub fn with_form_field(self, key: impl Into<String>, value: impl Into<String>) -> Self {
+    let (key, value) = (key.into(), value.into());
+    self.with_body(
+      move |body| matches!(body, RecordedBody::Form(form) if form.get(&key) == Some(&value)),
+    )
+  }
+
+  /// Requires the query string to contain the given parameter, for requests with and
+  /// without a body.
+  pub fn with_query_param(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+    self.query.push((key.into(), value.into()));
+    self
+  }
+
+  /// Requires the body to satisfy a custom predicate. Body matchers are combined,
+  /// so the request has to satisfy all of them.
+  pub fn with_body<F>(mut self, matcher: F) -> Self
+  where
+    F: Fn(&RecordedBody) -> bool + Send + Sync + 'static,
+  {
+    self.body = Some(match self.body.take() {
+      Some(previous) => Arc::new(move |body| previous(body) && matcher(body)),
+      None => Arc::new(matcher),
+    });
+    self
+  }
+
+  /// Replies with the given status and text body, see [`Expectation::respond_with_bytes`].
+  pub fn respond_with(self, status: u16, body: &str) -> Self {
+    self.respond_with_bytes(status, body)
+  }
+
+  /// Replies with the given status and body, statuses outside of the `2xx` range are
+  /// returned as [`HttpError::Status`] just like the real clients do.
+  pub fn respond_with_bytes(mut self, status: u16, body: impl Into<Vec<u8>>) -> Self {
+    self.response = MockResponse::Response {
+      status,
+      headers: Headers::new(),
+      body: body.into(),
+    };
+    self
+  }
+
+  pub fn respond_with_json(self, status: u16, body: &Value) -> Self {
+    self
+      .respond_with(status, &body.to_string())
+      .respond_with_header("content-type", "application/json")
+  }
+
+  /// Adds a header to the response, repeating a name adds another value.
+  pub fn respond_with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+    if let MockResponse::Response { headers, .. } = &mut self.response {
+      headers.append(name.into().to_lowercase(), value);
+    }
+    self
+  }
+
+  /// Fails the request with a transport error instead of replying.
+  pub fn fail_with(mut self, message: impl Into<String>) -> Self {
+    self.response = MockResponse::Transport(message.into());
+    self
+  }
+
+  /// Limits the expectation to exactly `times` calls. Once used up it no longer matches,
+  /// and [`MockHttpClient::verify`] fails if it was called fewer times.
+  ///
+  /// Without a limit the expectation matches any number of calls, but at least one.
+  pub fn times(mut self, times: u32) -> Self {
+    self.times = Some(times);
+    self
+  }
+
+  fn matches(&self, request: &RecordedRequest) -> bool {
+    self.method == request.method
+      && url_matches(&self.url, &request.url)
+      && self
+        .headers
+        .iter()
+        .all(|(name, value)| request.header(name) == Some(value.as_str()))
+      && self.query.iter().all(|param| request.query.contains(param))
+      && self.body.as_ref().is_none_or(|body| body(&request.body))
+  }
+}
+
+impl fmt::Debug for Expectation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Expectation")
+      .field("method", &self.method)
+      .field("url", &self.url)
+      .field("headers", &self.headers)
+      .field("query", &self.query)
+      .field("body", &self.body.as_ref().map(|_| ".."))
+      .field("response", &self.response)
+      .field("times", &self.times)
+      .finish()
+  }
+}
+
+/// Matches `url` against a pattern where `*` matches any sequence of characters.
+fn url_matches(pattern: &str, url: &str) -> bool {
+  let mut parts = pattern.split('*');
+  let first = parts.next().unwrap_or_default();
+  let Some(mut rest) = url.strip_prefix(first) else {
+    return false;
+  };
+
+  let parts = parts.collect::<Vec<_>>();
+  let Some((last, middle)) = parts.split_last() else {
+    return rest.is_empty();
+  };
+
+  for part in middle {
+    match rest.find(part) {
+      Some(index) => rest = &rest[index + part.len()..],
+      None => return false,
+    }
+  }
+
+  rest.ends_with(last)
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+  expectations: Vec<(Expectation, u32)>,
+  requests: Vec<RecordedRequest>,
+}
+
+/// A `BaseHttpClient` that serves canned responses to programmed expectations, for
+/// testing code built on top of the HTTP clients without network access.
+///
+/// Clones share their state, so a clone can be handed to the code under test while the
+/// original is used to verify the calls afterwards.
+///
+/// ```
+/// use gf_auth_http::{Expectation, Method, MockHttpClient};
+///
+/// let client = MockHttpClient::new();
+/// let sessions = client.expect(
+///   Expectation::new(Method::Post, "https://spark.gameforge.com/api/v1/auth/sessions")
+///     .respond_with(201, r#"{"token":"..."}"#)
+///     .times(1),
+/// );
+///
+/// // ... run the code under test ...
+/// # let _ = sessions;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockHttpClient {
+  state: Arc<Mutex<MockState>>,
+}
+
+/// Gives access to the number of calls matched by an [`Expectation`].
+#[derive(Debug, Clone)]
+pub struct ExpectationHandle {
+  state: Arc<Mutex<MockState>>,
+  index: usize,
+}
+
+impl ExpectationHandle {
+  pub fn calls(&self) -> u32 {
+    lock(&self.state).expectations[self.index].1
+  }
+}
+
+fn lock(state: &Mutex<MockState>) -> MutexGuard<'_, MockState> {
+  state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl MockHttpClient {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Registers an expectation, expectations are matched in the order they were added.
+  pub fn expect(&self, expectation: Expectation) -> ExpectationHandle {
+    let mut state = lock(&self.state);
+    state.expectations.push((expectation, 0));
+
+    ExpectationHandle {
+      state: self.state.clone(),
+      index: state.expectations.len() - 1,
+    }
+  }
+
+  /// All requests received so far, including the ones that didn't match any expectation.
+  pub fn requests(&self) -> Vec<RecordedRequest> {
+    lock(&self.state).requests.clone()
+  }
+
+  /// Panics if any expectation was called fewer times than required.
+  pub fn verify(&self) {
+    let state = lock(&self.state);
+    let unsatisfied = state
+      .expectations
+      .iter()
+      .filter(|(expectation, calls)| match expectation.times {
+        Some(times) => *calls != times,
+        None => *calls == 0,
+      })
+      .map(|(expectation, calls)| {
+        format!(
+          "{} {} (called {calls} times, expected {})",
+          expectation.method,
+          expectation.url,
+          expectation
+            .times
+            .map_or("at least once".to_string(), |times| format!(
+              "{times} times"
+            ))
+        )
+      })
+      .collect::<Vec<_>>();
+
+    if !unsatisfied.is_empty() {
+      panic!("unsatisfied expectations:\n{}", unsatisfied.join("\n"));
+    }
+  }
+
+  fn handle(&self, request: &HttpRequest) -> Result<HttpResponse, HttpError<MockError>> {
+    let method = request.method;
+    let (url, body) = match &request.body {
+      Body::Empty if request.query.is_empty() => (request.url.clone(), RecordedBody::None),
+      Body::Empty => (
+        request.url.clone(),
+        RecordedBody::Query(RecordedBody::pairs(&request.query)),
+      ),
+      Body::Json(value) => (request.full_url(), RecordedBody::Json(value.clone())),
+      Body::Form(pairs) => (
+        request.full_url(),
+        RecordedBody::Form(RecordedBody::pairs(pairs)),
+      ),
+      Body::Bytes(bytes) => (request.full_url(), RecordedBody::Bytes(bytes.clone())),
+      Body::Multipart(multipart) => (
+        request.full_url(),
+        RecordedBody::Multipart(multipart.clone()),
+      ),
+    };
+    let query = Url::parse(&request.full_url())
+      .map(|url| url.query_pairs().into_owned().collect())
+      .unwrap_or_default();
+    let request = RecordedRequest {
+      method,
+      url,
+      headers: request.headers.clone(),
+      body,
+      query,
+    };
+
+    let mut state = lock(&self.state);
+    state.requests.push(request.clone());
+
+    let matched = state.expectations.iter_mut().find(|(expectation, calls)| {
+      expectation.times.is_none_or(|times| *calls < times) && expectation.matches(&request)
+    });
+
+    let Some((expectation, calls)) = matched else {
+      return Err(HttpError::Client(MockError::Unmatched {
+        method,
+        url: request.url,
+      }));
+    };
+    *calls += 1;
+
+    match &expectation.response {
+      MockResponse::Response {
+        status,
+        headers,
+        body,
+      } if (200..300).contains(status) => {
+        Ok(HttpResponse::new(*status, headers.clone(), body.clone()))
+      }
+      MockResponse::Response {
+        status,
+        headers,
+        body,
+      } => Err(HttpError::from_status(
+        *status,
+        headers.clone(),
+        body.clone(),
+      )),
+      MockResponse::Transport(message) => {
+        Err(HttpError::Client(MockError::Transport(message.clone())))
+      }
+    }
+  }
+}
+
+flavoured! {
+  #[maybe_async]
+  impl BaseHttpClient for MockHttpClient {
+    type Error = HttpError<MockError>;
+
+    async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Self::Error> {
+      self.handle(request)
+    }
+  }
+
+  #[cfg(test)]
+  mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::common::{Form, Headers, HttpError, Method, Query};
+    use crate::mock::{Expectation, MockError, MockHttpClient};
+
+    const SESSIONS_URL: &str = "https://spark.gameforge.com/api/v1/auth/sessions";
+
+    #[maybe_async]
+    #[async_test]
+    async fn serves_matching_expectations() {
+      let client = MockHttpClient::new();
+      let login = client.expect(
+        Expectation::new(Method::Post, SESSIONS_URL)
+          .with_header("content-type", "application/json")
+          .with_json_body(json!({ "email": "user@example.com" }))
+          .respond_with_json(201, &json!({ "token": "abc" }))
+          .times(1),
+      );
+
+      let mut headers = Headers::new();
+      headers.insert("Content-Type".to_string(), "application/json".to_string());
+      let response = client
+        .post(
+          SESSIONS_URL,
+          Some(&headers),
+          &json!({ "email": "user@example.com" }),
+        )
+        .await
+        .unwrap();
+
+      assert_eq!(response.status(), 201);
+      assert_eq!(response.body(), r#"{"token":"abc"}"#);
+      assert_eq!(login.calls(), 1);
+      client.verify();
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn rejects_unmatched_requests() {
+      let client = MockHttpClient::new();
+      client.expect(
+        Expectation::new(Method::Get, "https://spark.gameforge.com/*")
+          .with_query_param("locale", "en"),
+      );
+
+      let mut query = Query::new();
+      query.insert("locale", "de");
+      let result = client.get(SESSIONS_URL, None, &query).await;
+
+      assert!(matches!(
+        result,
+        Err(HttpError::Client(MockError::Unmatched {
+          method: Method::Get,
+          ..
+        }))
+      ));
+      assert_eq!(client.requests().len(), 1);
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn returns_error_statuses_and_exhausts_expectations() {
+      let client = MockHttpClient::new();
+      client.expect(
+        Expectation::new(Method::Post, SESSIONS_URL)
+          .with_form_field("password", "wrong")
+          .respond_with(403, "")
+          .respond_with_header("Retry-After", "5")
+          .times(1),
+      );
+
+      let mut form = Form::new();
+      form.insert("password", "wrong");
+      let first = client.post_form(SESSIONS_URL, None, &form).await;
+      let second = client.post_form(SESSIONS_URL, None, &form).await;
+
+      match first {
+        Err(HttpError::Status { status, headers, .. }) => {
+          assert_eq!(status, 403);
+          assert_eq!(headers.get("retry-after").unwrap(), "5");
+        }
+        _ => panic!("expected a status error"),
+      }
+      assert!(matches!(
+        second,
+        Err(HttpError::Client(MockError::Unmatched { .. }))
+      ));
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn replies_with_binary_bodies() {
+      let client = MockHttpClient::new();
+      client.expect(
+        Expectation::new(Method::Get, SESSIONS_URL).respond_with_bytes(200, [0x89, b'P', 0]),
+      );
+
+      let response = client.get(SESSIONS_URL, None, &Query::new()).await;
+      assert_eq!(response.unwrap().bytes(), [0x89, b'P', 0]);
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn matches_query_params_of_requests_with_a_body() {
+      let client = MockHttpClient::new();
+      client.expect(
+        Expectation::new(Method::Post, "https://spark.gameforge.com/*")
+          .with_query_param("locale", "en")
+          .with_query_param("page", "2"),
+      );
+
+      let request = HttpRequest::new(Method::Post, format!("{SESSIONS_URL}?locale=en"))
+        .with_query("page", "2")
+        .with_json(json!({ "email": "user@example.com" }));
+      let response = client.send(&request).await;
+
+      assert!(response.is_ok());
+      assert_eq!(
+        client.requests()[0].query,
+        [("locale".into(), "en".into()), ("page".into(), "2".into())]
+      );
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::common::Method;
+  use crate::mock::{url_matches, Expectation, MockHttpClient};
+
+  const SESSIONS_URL: &str = "https://spark.gameforge.com/api/v1/auth/sessions";
+
+  #[test]
+  fn matches_url_patterns() {
+    assert!(url_matches(SESSIONS_URL, SESSIONS_URL));
+    assert!(!url_matches(
+      SESSIONS_URL,
+      "https://spark.gameforge.com/api/v1/auth"
+    ));
+    assert!(url_matches("https://spark.gameforge.com/*", SESSIONS_URL));
+    assert!(url_matches("*/auth/*", SESSIONS_URL));
+    assert!(url_matches(
+      "https://*.gameforge.com/*/sessions",
+      SESSIONS_URL
+    ));
+    assert!(!url_matches("https://*.gameforge.com/*/thin", SESSIONS_URL));
+  }
+
+  #[test]
+  #[should_panic(expected = "unsatisfied expectations")]
+  fn verify_panics_on_missing_calls() {
+    let client = MockHttpClient::new();
+    client.expect(Expectation::new(Method::Options, SESSIONS_URL).times(2));
+    client.verify();
+  }
+}
@@ -0,0 +1,69 @@
+//! The asynchronous flavour of the HTTP clients.
+
+use std::io;
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures_lite::Stream;
+
+pub use crate::common::nonblocking::{BaseHttpClient, StreamingResponse};
+#[cfg(feature = "client-reqwest")]
+pub use crate::reqwest::{ReqwestClient as HttpClient, ReqwestClientError as ClientError};
+// reqwest stays the default async client when both backends are enabled
+#[cfg(all(
+  feature = "client-async-io",
+  not(any(feature = "client-reqwest", feature = "client-hyper"))
+))]
+pub use crate::hyper::{AsyncIoClient as HttpClient, HyperClientError as ClientError};
+#[cfg(all(feature = "client-hyper", not(feature = "client-reqwest")))]
+pub use crate::hyper::{HyperClient as HttpClient, HyperClientError as ClientError};
+
+/// The flavour specific items used by the code compiled with `flavoured!`.
+pub(crate) mod prelude {
+  pub(crate) use maybe_async::must_be_async as maybe_async;
+  #[cfg(test)]
+  pub(crate) use tokio::test as async_test;
+
+  #[cfg(feature = "tracing")]
+  pub(crate) use super::in_span;
+  pub(crate) use super::sleep;
+  pub(crate) use super::{buffered_body, BaseHttpClient, ResponseBody, StreamingResponse};
+}
+
+/// The body of a [`StreamingResponse`], yielding the chunks as they are received.
+pub type ResponseBody = Pin<Box<dyn Stream<Item = io::Result<Vec<u8>>> + Send>>;
+
+/// A body yielding the whole buffered content at once.
+pub(crate) fn buffered_body(body: Vec<u8>) -> ResponseBody {
+  Box::pin(futures_lite::stream::once(Ok(body)))
+}
+
+/// Waits for the given duration without depending on the timers of a specific runtime.
+pub(crate) async fn sleep(duration: Duration) {
+  futures_timer::Delay::new(duration).await
+}
+
+/// Runs the request returned by `send` inside the given span.
+#[cfg(feature = "tracing")]
+pub(crate) async fn in_span<F, Fut>(span: &tracing::Span, send: F) -> Fut::Output
+where
+  F: FnOnce() -> Fut,
+  Fut: std::future::Future,
+{
+  use tracing::Instrument;
+
+  send().instrument(span.clone()).await
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::{Duration, Instant};
+
+  #[test]
+  fn sleeps_outside_of_tokio() {
+    let started = Instant::now();
+    smol::block_on(super::sleep(Duration::from_millis(20)));
+    async_std::task::block_on(super::sleep(Duration::from_millis(20)));
+    assert!(started.elapsed() >= Duration::from_millis(40));
+  }
+}
@@ -0,0 +1,585 @@
+// the pins are checked by the clients with TLS
+#![cfg_attr(
+  not(any(
+    feature = "client-hyper",
+    feature = "client-async-io",
+    feature = "__reqwest-native-tls",
+    feature = "ureq-native-tls",
+    feature = "ureq-rustls-tls",
+    feature = "ureq-rustls-tls-native-certs",
+    feature = "reqwest-rustls-tls"
+  )),
+  allow(dead_code)
+)]
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+#[cfg(any(test, feature = "__reqwest-native-tls"))]
+use std::net::IpAddr;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::digest::{digest, SHA256};
+
+use crate::config::ConfigError;
+
+/// SHA-256 digest of the `SubjectPublicKeyInfo` of a certificate, the key pins of HPKP.
+///
+/// The pin of a server can be computed with:
+/// ```text
+/// openssl s_client -connect spark.gameforge.com:443 </dev/null \
+///   | openssl x509 -pubkey -noout | openssl pkey -pubin -outform der \
+///   | openssl dgst -sha256 -binary | base64
+/// ```
+///
+/// The clients using rustls match the pins against the chain of certificates they verified,
+/// up to its root. native-tls only exposes the certificate of the server, so the reqwest
+/// and ureq clients using it can only pin the key of the server itself. reqwest doesn't
+/// tell which host it connects to either, so its key has to match the pins of every host
+/// the certificate is valid for.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Pin([u8; 32]);
+
+impl Pin {
+  pub fn from_sha256(digest: [u8; 32]) -> Self {
+    Pin(digest)
+  }
+
+  /// Parses the base64 encoded digest, optionally prefixed with `sha256/`.
+  pub fn parse(pin: &str) -> Result<Self, ConfigError> {
+    let encoded = pin.strip_prefix("sha256/").unwrap_or(pin);
+    STANDARD
+      .decode(encoded)
+      .ok()
+      .and_then(|digest| digest.try_into().ok())
+      .map(Pin)
+      .ok_or_else(|| ConfigError::Tls(format!("invalid pin {pin}")))
+  }
+
+  /// Pin of a DER encoded `SubjectPublicKeyInfo`.
+  pub fn from_public_key(spki: &[u8]) -> Self {
+    let mut pin = [0; 32];
+    pin.copy_from_slice(digest(&SHA256, spki).as_ref());
+    Pin(pin)
+  }
+
+  /// Pin of the public key of a DER encoded certificate.
+  pub fn from_certificate(der: &[u8]) -> Result<Self, ConfigError> {
+    subject_public_key_info(der)
+      .map(Pin::from_public_key)
+      .ok_or_else(|| ConfigError::Tls("malformed certificate".to_string()))
+  }
+}
+
+impl fmt::Display for Pin {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "sha256/{}", STANDARD.encode(self.0))
+  }
+}
+
+impl fmt::Debug for Pin {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("Pin").field(&self.to_string()).finish()
+  }
+}
+
+/// None of the certificates of the verified chain of the server matched the keys pinned
+/// for its host, which fails the handshake before the request is sent.
+///
+/// It's found in the error of the client with [`HttpError::pin_mismatch`].
+///
+/// [`HttpError::pin_mismatch`]: crate::HttpError::pin_mismatch
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("the certificates of {host} don't match any of its pinned keys")]
+pub struct PinMismatch {
+  pub host: String,
+}
+
+impl PinMismatch {
+  /// Looks for the mismatch in the error and its sources.
+  pub fn find<'a>(err: &'a (dyn Error + 'static)) -> Option<&'a PinMismatch> {
+    let mut next = Some(err);
+    while let Some(err) = next {
+      if let Some(mismatch) = err.downcast_ref::<PinMismatch>() {
+        return Some(mismatch);
+      }
+      // the source of an I/O error is the source of the error it wraps, skipping it
+      if let Some(inner) = err.downcast_ref::<io::Error>().and_then(io::Error::get_ref) {
+        if let Some(mismatch) = PinMismatch::find(inner) {
+          return Some(mismatch);
+        }
+      }
+      #[cfg(any(
+        feature = "client-hyper",
+        feature = "client-async-io",
+        feature = "ureq-rustls-tls",
+        feature = "ureq-rustls-tls-native-certs",
+        feature = "reqwest-rustls-tls"
+      ))]
+      if let Some(rustls::Error::InvalidCertificate(rustls::CertificateError::Other(other))) =
+        err.downcast_ref::<rustls::Error>()
+      {
+        return other.0.downcast_ref::<PinMismatch>();
+      }
+      next = err.source();
+    }
+    None
+  }
+}
+
+/// Pins of the host, which are the ones of the host itself or of its closest parent
+/// domain with pins.
+fn host_pins<'a>(pins: &'a HashMap<String, Vec<Pin>>, host: &str) -> Option<&'a [Pin]> {
+  let host = host.trim_end_matches('.').to_ascii_lowercase();
+  let mut domain = host.as_str();
+  loop {
+    if let Some(pins) = pins.get(domain) {
+      return Some(pins);
+    }
+    domain = domain.split_once('.')?.1;
+  }
+}
+
+/// Checks that one of the keys of the verified certificates of `host` is pinned.
+pub(crate) fn verify_pins<I>(
+  pins: &HashMap<String, Vec<Pin>>,
+  host: &str,
+  keys: I,
+) -> Result<(), PinMismatch>
+where
+  I: IntoIterator<Item = Pin>,
+{
+  let Some(expected) = host_pins(pins, host) else {
+    return Ok(());
+  };
+  let pinned = keys.into_iter().any(|pin| expected.contains(&pin));
+  match pinned {
+    true => Ok(()),
+    false => Err(PinMismatch {
+      host: host.to_string(),
+    }),
+  }
+}
+
+/// Checks the key of the DER encoded certificate against the pins of every host it's valid
+/// for, when the host it was sent by isn't known.
+///
+/// The certificates without a name are rejected, since any of the pins could apply.
+#[cfg(any(test, feature = "__reqwest-native-tls"))]
+pub(crate) fn verify_certificate_pins(
+  pins: &HashMap<String, Vec<Pin>>,
+  certificate: &[u8],
+) -> Result<(), PinMismatch> {
+  let key = Pin::from_certificate(certificate).ok();
+  let names = certificate_names(certificate).unwrap_or_default();
+  if names.is_empty() {
+    return Err(PinMismatch {
+      host: String::new(),
+    });
+  }
+  for name in &names {
+    verify_pins(pins, name, key)?;
+    // a wildcard is also valid for the pinned hosts one level below its domain
+    if let Some(domain) = name.strip_prefix("*.") {
+      let hosts = pins.keys().filter(|host| {
+        host
+          .split_once('.')
+          .is_some_and(|(_, parent)| parent == domain)
+      });
+      for host in hosts {
+        verify_pins(pins, host, key)?;
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Splits the DER element at the start of the input into the element, with its header,
+/// and the rest of the input.
+fn der_element(der: &[u8]) -> Option<(&[u8], &[u8])> {
+  let (&first, rest) = der.get(1..)?.split_first()?;
+  let (length, header) = match first {
+    length @ 0..=0x7f => (length as usize, 2),
+    0x81..=0x84 => {
+      let size = (first & 0x7f) as usize;
+      let length = rest
+        .get(..size)?
+        .iter()
+        .fold(0, |length, &byte| length << 8 | byte as usize);
+      (length, 2 + size)
+    }
+    _ => return None,
+  };
+  let end = header.checked_add(length)?;
+  (der.len() >= end).then(|| der.split_at(end))
+}
+
+/// Content of the DER element at the start of the input.
+fn der_content(der: &[u8]) -> Option<&[u8]> {
+  let (element, _) = der_element(der)?;
+  let header = match element[1] {
+    0..=0x7f => 2,
+    length => 2 + (length & 0x7f) as usize,
+  };
+  Some(&element[header..])
+}
+
+/// Iterates over the DER elements of the input.
+#[cfg(any(test, feature = "__reqwest-native-tls"))]
+fn der_elements(mut der: &[u8]) -> impl Iterator<Item = &[u8]> {
+  std::iter::from_fn(move || {
+    let (element, rest) = der_element(der)?;
+    der = rest;
+    Some(element)
+  })
+}
+
+/// The fields of a DER encoded X.509 certificate from its subject on: the subject, its
+/// public key and the optional unique ids and extensions.
+fn subject_fields(certificate: &[u8]) -> Option<&[u8]> {
+  let tbs = der_content(der_content(certificate)?)?;
+  // the version is an optional explicitly tagged field
+  let mut fields = match tbs.first()? {
+    0xa0 => der_element(tbs)?.1,
+    _ => tbs,
+  };
+  // serial number, signature algorithm, issuer and validity
+  for _ in 0..4 {
+    fields = der_element(fields)?.1;
+  }
+  Some(fields)
+}
+
+/// The `SubjectPublicKeyInfo` of a DER encoded X.509 certificate.
+fn subject_public_key_info(certificate: &[u8]) -> Option<&[u8]> {
+  let fields = der_element(subject_fields(certificate)?)?.1;
+  Some(der_element(fields)?.0)
+}
+
+/// Host names and IP addresses of the subject alternative names of a DER encoded X.509
+/// certificate, or its common name when it has none.
+#[cfg(any(test, feature = "__reqwest-native-tls"))]
+fn certificate_names(certificate: &[u8]) -> Option<Vec<String>> {
+  const SUBJECT_ALT_NAME: &[u8] = &[0x06, 0x03, 0x55, 0x1d, 0x11];
+  const COMMON_NAME: &[u8] = &[0x06, 0x03, 0x55, 0x04, 0x03];
+
+  let mut fields = der_elements(subject_fields(certificate)?);
+  let subject = fields.next()?;
+  let mut names = Vec::new();
+  // the extensions are the only field explicitly tagged with 3
+  if let Some(extensions) = fields.find(|field| field[0] == 0xa3) {
+    for extension in der_elements(der_content(der_content(extensions)?)?) {
+      let mut parts = der_elements(der_content(extension)?);
+      if parts.next() != Some(SUBJECT_ALT_NAME) {
+        continue;
+      }
+      // skips the optional critical flag
+      let value = parts.find(|part| part[0] == 0x04)?;
+      for name in der_elements(der_content(der_content(value)?)?) {
+        let content = der_content(name)?;
+        match (name[0], content.len()) {
+          (0x82, _) => names.push(String::from_utf8_lossy(content).to_ascii_lowercase()),
+          (0x87, 4) => names.push(IpAddr::from(<[u8; 4]>::try_from(content).ok()?).to_string()),
+          (0x87, 16) => names.push(IpAddr::from(<[u8; 16]>::try_from(content).ok()?).to_string()),
+          _ => {}
+        }
+      }
+    }
+  }
+  if names.is_empty() {
+    let attributes = der_elements(der_content(subject)?)
+      .flat_map(|set| der_content(set).into_iter().flat_map(der_elements));
+    for attribute in attributes {
+      let mut parts = der_elements(der_content(attribute)?);
+      if parts.next() == Some(COMMON_NAME) {
+        let value = der_content(parts.next()?)?;
+        names.push(String::from_utf8_lossy(value).to_ascii_lowercase());
+      }
+    }
+  }
+  Some(names)
+}
+
+#[cfg(any(
+  feature = "client-hyper",
+  feature = "client-async-io",
+  feature = "ureq-rustls-tls",
+  feature = "ureq-rustls-tls-native-certs",
+  feature = "reqwest-rustls-tls"
+))]
+mod verifier {
+  use std::collections::HashMap;
+  use std::sync::Arc;
+
+  use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+  use rustls::client::{WantsClientCert, WebPkiServerVerifier};
+  use rustls::crypto::WebPkiSupportedAlgorithms;
+  use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+  use rustls::{
+    CertificateError, ClientConfig, ConfigBuilder, DigitallySignedStruct, OtherError,
+    RootCertStore, SignatureScheme, WantsVerifier,
+  };
+  use webpki::{EndEntityCert, KeyUsage, VerifiedPath};
+
+  use super::{host_pins, verify_pins, Pin, PinMismatch};
+  use crate::config::ConfigError;
+
+  /// Checks the pins against the chain of certificates once it has been verified.
+  #[derive(Debug)]
+  struct PinningVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    roots: Arc<RootCertStore>,
+    algorithms: WebPkiSupportedAlgorithms,
+    pins: HashMap<String, Vec<Pin>>,
+  }
+
+  impl PinningVerifier {
+    /// Looks for a valid chain from the certificate to the roots with a pinned key.
+    ///
+    /// The chain verified by rustls isn't exposed, so it's built again by webpki which
+    /// tries the other valid chains while the pins don't match.
+    fn verify_chain(
+      &self,
+      end_entity: &CertificateDer<'_>,
+      intermediates: &[CertificateDer<'_>],
+      host: &str,
+      now: UnixTime,
+    ) -> Result<(), PinMismatch> {
+      let mismatch = || PinMismatch {
+        host: host.to_string(),
+      };
+      let certificate = EndEntityCert::try_from(end_entity).map_err(|_| mismatch())?;
+      let verify_path = |path: &VerifiedPath<'_>| {
+        let anchor = path.anchor().subject_public_key_info.as_ref();
+        let keys = std::iter::once(path.end_entity().subject_public_key_info())
+          .chain(
+            path
+              .intermediate_certificates()
+              .map(|cert| cert.subject_public_key_info()),
+          )
+          .map(|spki| Pin::from_public_key(spki.as_ref()))
+          .chain(std::iter::once(Pin::from_public_key(&der_sequence(anchor))));
+        verify_pins(&self.pins, host, keys).map_err(|_| webpki::Error::UnknownIssuer)
+      };
+      certificate
+        .verify_for_usage(
+          self.algorithms.all,
+          &self.roots.roots,
+          intermediates,
+          now,
+          KeyUsage::server_auth(),
+          None,
+          Some(&verify_path),
+        )
+        .map(|_| ())
+        .map_err(|_| mismatch())
+    }
+  }
+
+  impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+      &self,
+      end_entity: &CertificateDer<'_>,
+      intermediates: &[CertificateDer<'_>],
+      server_name: &ServerName<'_>,
+      ocsp_response: &[u8],
+      now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+      let verified = self.inner.verify_server_cert(
+        end_entity,
+        intermediates,
+        server_name,
+        ocsp_response,
+        now,
+      )?;
+      let host = server_name.to_str();
+      if host_pins(&self.pins, &host).is_some() {
+        self
+          .verify_chain(end_entity, intermediates, &host, now)
+          .map_err(|err| {
+            rustls::Error::InvalidCertificate(CertificateError::Other(OtherError(Arc::new(err))))
+          })?;
+      }
+      Ok(verified)
+    }
+
+    fn verify_tls12_signature(
+      &self,
+      message: &[u8],
+      cert: &CertificateDer<'_>,
+      dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+      self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+      &self,
+      message: &[u8],
+      cert: &CertificateDer<'_>,
+      dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+      self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+      self.inner.supported_verify_schemes()
+    }
+  }
+
+  /// DER encoded `SEQUENCE` of the content.
+  fn der_sequence(content: &[u8]) -> Vec<u8> {
+    let mut der = vec![0x30];
+    match content.len() {
+      length @ 0..=0x7f => der.push(length as u8),
+      length => {
+        let bytes = length.to_be_bytes();
+        let bytes = &bytes[bytes.iter().take_while(|&&byte| byte == 0).count()..];
+        der.push(0x80 | bytes.len() as u8);
+        der.extend_from_slice(bytes);
+      }
+    }
+    der.extend_from_slice(content);
+    der
+  }
+
+  /// Trusts the root certificates, also checking the pins when there are any.
+  pub(crate) fn with_pinned_roots(
+    builder: ConfigBuilder<ClientConfig, WantsVerifier>,
+    roots: RootCertStore,
+    pins: &HashMap<String, Vec<Pin>>,
+  ) -> Result<ConfigBuilder<ClientConfig, WantsClientCert>, ConfigError> {
+    if pins.is_empty() {
+      return Ok(builder.with_root_certificates(roots));
+    }
+
+    let roots = Arc::new(roots);
+    let provider = builder.crypto_provider().clone();
+    let inner = WebPkiServerVerifier::builder_with_provider(roots.clone(), provider.clone())
+      .build()
+      .map_err(|err| ConfigError::Tls(err.to_string()))?;
+    let verifier = PinningVerifier {
+      inner,
+      roots,
+      algorithms: provider.signature_verification_algorithms,
+      pins: pins.clone(),
+    };
+    Ok(
+      builder
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier)),
+    )
+  }
+}
+
+#[cfg(any(
+  feature = "client-hyper",
+  feature = "client-async-io",
+  feature = "ureq-rustls-tls",
+  feature = "ureq-rustls-tls-native-certs",
+  feature = "reqwest-rustls-tls"
+))]
+pub(crate) use verifier::with_pinned_roots;
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+
+  use super::{verify_certificate_pins, verify_pins, Pin, PinMismatch};
+
+  const CERTIFICATE: &str = include_str!("../../../resources/client.pem");
+
+  fn certificate_der() -> Vec<u8> {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    let encoded = CERTIFICATE
+      .lines()
+      .skip_while(|line| !line.starts_with("-----BEGIN"))
+      .skip(1)
+      .take_while(|line| !line.starts_with("-----END"))
+      .collect::<String>();
+    STANDARD.decode(encoded).unwrap()
+  }
+
+  #[test]
+  fn parses_and_prints_pins() {
+    let pin = Pin::parse("sha256/AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=").unwrap();
+    assert_eq!(pin, Pin::from_sha256([0; 32]));
+    assert_eq!(
+      pin.to_string(),
+      "sha256/AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="
+    );
+    assert!(Pin::parse("sha256/AAAA").is_err());
+    assert!(Pin::from_certificate(b"\x30\x03\x02").is_err());
+  }
+
+  #[test]
+  fn verifies_pins_of_host_and_subdomains() {
+    let der = certificate_der();
+    let pin = Pin::from_certificate(&der).unwrap();
+    let other = Pin::from_sha256([0; 32]);
+    let pins = HashMap::from([
+      ("gameforge.com".to_string(), vec![other, pin]),
+      ("mismatch.gameforge.com".to_string(), vec![other]),
+    ]);
+
+    assert!(verify_pins(&pins, "spark.gameforge.com", [pin]).is_ok());
+    assert!(verify_pins(&pins, "example.com", [pin]).is_ok());
+    let err = verify_pins(&pins, "mismatch.gameforge.com", [pin]).unwrap_err();
+    assert_eq!(err.host, "mismatch.gameforge.com");
+
+    let io = std::io::Error::other(err.clone());
+    assert_eq!(PinMismatch::find(&io), Some(&err));
+  }
+
+  #[test]
+  fn verifies_pins_of_every_name_of_the_certificate() {
+    use rcgen::{CertificateParams, DnType, KeyPair};
+
+    let key = KeyPair::generate().unwrap();
+    let names = vec![
+      "spark.gameforge.com".to_string(),
+      "*.Example.com".to_string(),
+    ];
+    let certificate = CertificateParams::new(names)
+      .unwrap()
+      .self_signed(&key)
+      .unwrap();
+    let der = certificate.der().as_ref();
+    let pin = Pin::from_certificate(der).unwrap();
+    let other = Pin::from_sha256([0; 32]);
+
+    let verify = |host: &str, pin: Pin| {
+      let pins = HashMap::from([(host.to_string(), vec![pin])]);
+      verify_certificate_pins(&pins, der).map_err(|err| err.host)
+    };
+    assert_eq!(verify("gameforge.com", pin), Ok(()));
+    assert_eq!(verify("example.com", pin), Ok(()));
+    assert_eq!(verify("unrelated.com", other), Ok(()));
+    assert_eq!(verify("deeper.login.example.com", other), Ok(()));
+    assert_eq!(
+      verify("gameforge.com", other),
+      Err("spark.gameforge.com".to_string())
+    );
+    assert_eq!(
+      verify("example.com", other),
+      Err("*.example.com".to_string())
+    );
+    assert_eq!(
+      verify("login.example.com", other),
+      Err("login.example.com".to_string())
+    );
+
+    // the common name is only used without alternative names
+    let mut params = CertificateParams::new(Vec::new()).unwrap();
+    params
+      .distinguished_name
+      .push(DnType::CommonName, "gameforge.com");
+    let certificate = params.self_signed(&key).unwrap();
+    let pins = HashMap::from([("gameforge.com".to_string(), vec![other])]);
+    let err = verify_certificate_pins(&pins, certificate.der()).unwrap_err();
+    assert_eq!(err.host, "gameforge.com");
+  }
+}
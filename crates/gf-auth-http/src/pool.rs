@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+use crate::config::{ConfigError, FromConfig, HttpClientConfig, Proxy};
+
+/// Proxy and local address the requests of an account are sent from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountRoute {
+  pub proxy: Option<Proxy>,
+  pub local_address: Option<IpAddr>,
+}
+
+impl AccountRoute {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn with_proxy(mut self, proxy: Proxy) -> Self {
+    self.proxy = Some(proxy);
+    self
+  }
+
+  pub fn with_local_address(mut self, address: IpAddr) -> Self {
+    self.local_address = Some(address);
+    self
+  }
+}
+
+/// Clients of several accounts, created from a shared configuration with the route of
+/// each account applied on top of it.
+///
+/// The client of an account is created the first time it is requested and reused
+/// afterwards, keeping its open connections, until the route of the account changes.
+/// Cloning the pool shares its clients.
+#[derive(Debug, Clone)]
+pub struct ClientPool<C> {
+  config: HttpClientConfig,
+  clients: Arc<Mutex<HashMap<String, (AccountRoute, C)>>>,
+}
+
+impl<C> Default for ClientPool<C> {
+  fn default() -> Self {
+    Self::new(HttpClientConfig::default())
+  }
+}
+
+impl<C> ClientPool<C> {
+  pub fn new(config: HttpClientConfig) -> Self {
+    Self {
+      config,
+      clients: Arc::default(),
+    }
+  }
+
+  /// Drops the client of the account, returning whether there was one.
+  pub fn remove(&self, account: &str) -> bool {
+    self.clients.lock().unwrap().remove(account).is_some()
+  }
+
+  pub fn len(&self) -> usize {
+    self.clients.lock().unwrap().len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.clients.lock().unwrap().is_empty()
+  }
+}
+
+impl<C: FromConfig + Clone> ClientPool<C> {
+  /// Returns the client of the account, creating it when the account has none yet or
+  /// when its route differs from the one the client was created with.
+  pub fn get(&self, account: &str, route: &AccountRoute) -> Result<C, ConfigError> {
+    if let Some((current, client)) = self.clients.lock().unwrap().get(account) {
+      if current == route {
+        return Ok(client.clone());
+      }
+    }
+
+    // built without holding the lock, as creating a client can take a while
+    let mut config = self.config.clone();
+    config.proxy = route.proxy.clone().or(config.proxy);
+    config.local_address = route.local_address.or(config.local_address);
+    let client = C::from_config(&config)?;
+
+    let mut clients = self.clients.lock().unwrap();
+    let entry = clients
+      .entry(account.to_string())
+      .or_insert_with(|| (route.clone(), client.clone()));
+    // another thread may have created a client for the same route in the meantime
+    if entry.0 != *route {
+      *entry = (route.clone(), client);
+    }
+    Ok(entry.1.clone())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::net::{IpAddr, Ipv4Addr};
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  use super::{AccountRoute, ClientPool};
+  use crate::config::{ConfigError, FromConfig, HttpClientConfig, Proxy};
+
+  static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+  #[derive(Debug, Clone)]
+  struct ConfiguredClient {
+    config: HttpClientConfig,
+    id: usize,
+  }
+
+  impl FromConfig for ConfiguredClient {
+    fn from_config(config: &HttpClientConfig) -> Result<Self, ConfigError> {
+      if config.local_address == Some(IpAddr::V4(Ipv4Addr::UNSPECIFIED)) {
+        return Err(ConfigError::Unsupported("local address"));
+      }
+      Ok(Self {
+        config: config.clone(),
+        id: CREATED.fetch_add(1, Ordering::SeqCst),
+      })
+    }
+  }
+
+  #[test]
+  fn reuses_clients_per_account() {
+    let proxy = Proxy::new("socks5://proxy.local:1080")
+      .unwrap()
+      .with_auth("user", "secret");
+    let pool = ClientPool::<ConfiguredClient>::new(
+      HttpClientConfig::new().with_local_address(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+    );
+    let direct = AccountRoute::new();
+    let proxied = AccountRoute::new().with_proxy(proxy.clone());
+
+    let first = pool.get("first", &direct).unwrap();
+    let second = pool.get("second", &proxied).unwrap();
+    assert_eq!(pool.get("first", &direct).unwrap().id, first.id);
+    assert_ne!(second.id, first.id);
+    assert_eq!(second.config.proxy, Some(proxy));
+    assert_eq!(
+      second.config.local_address,
+      Some(IpAddr::V4(Ipv4Addr::LOCALHOST))
+    );
+    assert_eq!(pool.len(), 2);
+
+    let moved = pool.get("first", &proxied).unwrap();
+    assert_ne!(moved.id, first.id);
+
+    assert!(pool.remove("first"));
+    assert!(!pool.remove("first"));
+    assert_eq!(pool.len(), 1);
+  }
+
+  #[test]
+  fn reports_configuration_errors() {
+    let pool = ClientPool::<ConfiguredClient>::default();
+    let route = AccountRoute::new().with_local_address(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+
+    assert!(matches!(
+      pool.get("account", &route),
+      Err(ConfigError::Unsupported(_))
+    ));
+    assert!(pool.is_empty());
+  }
+}
@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+use crate::common::{HttpError, HttpRequest, HttpResponse};
+
+/// Size and refill speed of a single token bucket.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quota {
+  /// Maximum number of requests which can be made in a burst, at least one.
+  pub burst: u32,
+  /// Time it takes to replenish a single request.
+  pub interval: Duration,
+}
+
+impl Quota {
+  /// Creates a quota, a `burst` of zero is raised to one as no request could ever be
+  /// made otherwise.
+  pub fn new(burst: u32, interval: Duration) -> Self {
+    Quota {
+      burst: burst.max(1),
+      interval,
+    }
+  }
+
+  pub fn per_second(requests: u32) -> Self {
+    Quota::new(requests, Duration::from_secs(1) / requests.max(1))
+  }
+
+  pub fn per_minute(requests: u32) -> Self {
+    Quota::new(requests, Duration::from_secs(60) / requests.max(1))
+  }
+
+  fn capacity(&self) -> f64 {
+    self.burst.max(1) as f64
+  }
+}
+
+/// What to do when a request would exceed one of the quotas.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RateLimitMode {
+  /// Block until the request fits into the budget again.
+  #[default]
+  Wait,
+  /// Fail immediately with [`HttpError::RateLimited`].
+  Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum BucketKey {
+  Global,
+  Host(String),
+  Tag(String),
+}
+
+#[derive(Debug)]
+struct Bucket {
+  tokens: f64,
+  updated: Instant,
+}
+
+impl Bucket {
+  fn full(quota: &Quota, now: Instant) -> Self {
+    Bucket {
+      tokens: quota.capacity(),
+      updated: now,
+    }
+  }
+
+  fn refill(&mut self, quota: &Quota, now: Instant) {
+    let elapsed = now.saturating_duration_since(self.updated);
+    let tokens = elapsed.as_secs_f64() / quota.interval.as_secs_f64().max(f64::EPSILON);
+
+    self.tokens = (self.tokens + tokens).min(quota.capacity());
+    self.updated = now;
+  }
+
+  fn wait_time(&self, quota: &Quota) -> Duration {
+    if self.tokens >= 1.0 {
+      Duration::ZERO
+    } else {
+      quota.interval.mul_f64(1.0 - self.tokens)
+    }
+  }
+}
+
+/// Shared token-bucket state used by every [`RateLimitClient`] created from it.
+///
+/// Quotas can be set globally, per request host and per tag (e.g. an account id),
+/// a request has to fit into all of the quotas which apply to it.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+  mode: RateLimitMode,
+  global: Option<Quota>,
+  per_host: Option<Quota>,
+  per_tag: Option<Quota>,
+  buckets: Mutex<HashMap<BucketKey, Bucket>>,
+}
+
+impl RateLimiter {
+  pub fn new(mode: RateLimitMode) -> Self {
+    RateLimiter {
+      mode,
+      ..Default::default()
+    }
+  }
+
+  pub fn with_global(mut self, quota: Quota) -> Self {
+    self.global = Some(quota);
+    self
+  }
+
+  pub fn with_per_host(mut self, quota: Quota) -> Self {
+    self.per_host = Some(quota);
+    self
+  }
+
+  pub fn with_per_tag(mut self, quota: Quota) -> Self {
+    self.per_tag = Some(quota);
+    self
+  }
+
+  pub fn mode(&self) -> RateLimitMode {
+    self.mode
+  }
+
+  /// Takes a token from every bucket that applies to the request, or returns how long
+  /// to wait until that is possible. No tokens are taken unless all buckets have one.
+  fn try_acquire(&self, host: Option<&str>, tag: Option<&str>) -> Result<(), Duration> {
+    let keys = [
+      self.global.map(|quota| (BucketKey::Global, quota)),
+      self
+        .per_host
+        .zip(host)
+        .map(|(quota, host)| (BucketKey::Host(host.to_owned()), quota)),
+      self
+        .per_tag
+        .zip(tag)
+        .map(|(quota, tag)| (BucketKey::Tag(tag.to_owned()), quota)),
+    ];
+
+    let now = Instant::now();
+    let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let mut wait = Duration::ZERO;
+    for (key, quota) in keys.iter().flatten() {
+      let bucket = buckets
+        .entry(key.clone())
+        .or_insert_with(|| Bucket::full(quota, now));
+      bucket.refill(quota, now);
+      wait = wait.max(bucket.wait_time(quota));
+    }
+
+    if !wait.is_zero() {
+      return Err(wait);
+    }
+
+    for (key, _) in keys.iter().flatten() {
+      if let Some(bucket) = buckets.get_mut(key) {
+        bucket.tokens -= 1.0;
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// A `BaseHttpClient` wrapper which throttles outgoing requests according to a
+/// [`RateLimiter`].
+///
+/// Clients created with [`RateLimitClient::with_tag`] share the limiter with the
+/// client they were created from, which allows to e.g. create one client per account
+/// while still enforcing the per host and global quotas across all of them.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimitClient<C> {
+  inner: C,
+  limiter: Arc<RateLimiter>,
+  tag: Option<String>,
+}
+
+impl<C> RateLimitClient<C> {
+  pub fn new(inner: C, limiter: RateLimiter) -> Self {
+    RateLimitClient {
+      inner,
+      limiter: Arc::new(limiter),
+      tag: None,
+    }
+  }
+
+  pub fn with_tag(&self, tag: impl Into<String>) -> Self
+  where
+    C: Clone,
+  {
+    RateLimitClient {
+      inner: self.inner.clone(),
+      limiter: self.limiter.clone(),
+      tag: Some(tag.into()),
+    }
+  }
+
+  pub fn inner(&self) -> &C {
+    &self.inner
+  }
+
+  pub fn limiter(&self) -> &RateLimiter {
+    &self.limiter
+  }
+
+  pub fn tag(&self) -> Option<&str> {
+    self.tag.as_deref()
+  }
+}
+
+flavoured! {
+  #[maybe_async]
+  async fn acquire<C, E: Error>(
+    client: &RateLimitClient<C>,
+    url: &str,
+  ) -> Result<(), HttpError<E>> {
+    let host = Url::parse(url)
+      .ok()
+      .and_then(|url| url.host_str().map(str::to_owned));
+
+    loop {
+      let wait = match client
+        .limiter
+        .try_acquire(host.as_deref(), client.tag.as_deref())
+      {
+        Ok(()) => return Ok(()),
+        Err(wait) => wait,
+      };
+
+      match client.limiter.mode {
+        RateLimitMode::Wait => {
+          log::debug!("Rate limit reached, waiting {wait:?}");
+          sleep(wait).await
+        }
+        RateLimitMode::Error => return Err(HttpError::RateLimited { retry_after: wait }),
+      }
+    }
+  }
+
+  #[maybe_async]
+  impl<C, E> BaseHttpClient for RateLimitClient<C>
+  where
+    C: BaseHttpClient<Error = HttpError<E>> + Sync,
+    E: Error + Send,
+  {
+    type Error = C::Error;
+
+    async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Self::Error> {
+      acquire(self, &request.url).await?;
+      self.inner.send(request).await
+    }
+
+    async fn send_streaming(&self, request: &HttpRequest) -> Result<StreamingResponse, Self::Error> {
+      acquire(self, &request.url).await?;
+      self.inner.send_streaming(request).await
+    }
+  }
+
+  #[cfg(test)]
+  mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::common::{HttpError, Query};
+    use crate::ratelimit::{Quota, RateLimitClient, RateLimitMode, RateLimiter};
+    use crate::testing::ScriptedClient;
+
+    const GF_URL: &str = "https://spark.gameforge.com/api/v1/auth/sessions";
+    const OTHER_URL: &str = "https://events2.gameforge.com/";
+
+    fn is_rate_limited<T, E: std::error::Error>(result: &Result<T, HttpError<E>>) -> bool {
+      matches!(result, Err(HttpError::RateLimited { .. }))
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn errors_when_global_budget_is_exhausted() {
+      let limiter =
+        RateLimiter::new(RateLimitMode::Error).with_global(Quota::new(2, Duration::from_secs(60)));
+      let client = RateLimitClient::new(ScriptedClient::default(), limiter);
+
+      let first = client.get(GF_URL, None, &Query::new()).await;
+      let second = client.get(OTHER_URL, None, &Query::new()).await;
+      let third = client.get(GF_URL, None, &Query::new()).await;
+
+      assert!(first.is_ok() && second.is_ok());
+      assert!(is_rate_limited(&third));
+      assert_eq!(client.inner().calls(), 2);
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn limits_hosts_independently() {
+      let limiter = RateLimiter::new(RateLimitMode::Error)
+        .with_per_host(Quota::new(1, Duration::from_secs(60)));
+      let client = RateLimitClient::new(ScriptedClient::default(), limiter);
+
+      let first = client.get(GF_URL, None, &Query::new()).await;
+      let other = client.get(OTHER_URL, None, &Query::new()).await;
+      let second = client.get(GF_URL, None, &Query::new()).await;
+
+      assert!(first.is_ok() && other.is_ok());
+      assert!(is_rate_limited(&second));
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn tagged_clients_share_the_limiter() {
+      let limiter =
+        RateLimiter::new(RateLimitMode::Error).with_per_tag(Quota::new(1, Duration::from_secs(60)));
+      let client = RateLimitClient::new(ScriptedClient::default(), limiter);
+      let first_account = client.with_tag("first");
+      let second_account = client.with_tag("second");
+
+      let untagged = client.get(GF_URL, None, &Query::new()).await;
+      let first = first_account.get(GF_URL, None, &Query::new()).await;
+      let second = second_account.get(GF_URL, None, &Query::new()).await;
+      let first_again = client
+        .with_tag("first")
+        .get(GF_URL, None, &Query::new())
+        .await;
+
+      assert!(untagged.is_ok() && first.is_ok() && second.is_ok());
+      assert!(is_rate_limited(&first_again));
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn waits_for_the_budget_to_refill() {
+      let limiter =
+        RateLimiter::new(RateLimitMode::Wait).with_global(Quota::new(1, Duration::from_millis(50)));
+      let client = RateLimitClient::new(ScriptedClient::default(), limiter);
+
+      let start = Instant::now();
+      let first = client.get(GF_URL, None, &Query::new()).await;
+      let second = client.get(GF_URL, None, &Query::new()).await;
+
+      assert!(first.is_ok() && second.is_ok());
+      assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn treats_empty_bursts_as_a_single_request() {
+      let quota = Quota {
+        burst: 0,
+        interval: Duration::from_millis(10),
+      };
+      assert_eq!(Quota::new(0, Duration::from_secs(1)).burst, 1);
+
+      let limiter = RateLimiter::new(RateLimitMode::Wait).with_global(quota);
+      let client = RateLimitClient::new(ScriptedClient::default(), limiter);
+
+      let first = client.get(GF_URL, None, &Query::new()).await;
+      let second = client.get(GF_URL, None, &Query::new()).await;
+
+      assert!(first.is_ok() && second.is_ok());
+    }
+  }
+}
@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use metrics::{
+  Counter, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+};
+
+type Values = Mutex<BTreeMap<String, Arc<AtomicU64>>>;
+
+/// Metrics recorder for tests, keeping the values of the counters and the number of
+/// values recorded by the histograms, by metric name and labels.
+///
+/// It's installed for the current thread with `metrics::set_default_local_recorder`.
+#[derive(Debug, Default)]
+pub struct MetricsRecorder {
+  counters: Values,
+  histograms: Values,
+}
+
+impl MetricsRecorder {
+  /// The counters as `name{label=value,...} count`, sorted.
+  pub fn counters(&self) -> Vec<String> {
+    let counters = self.counters.lock().unwrap();
+    counters
+      .iter()
+      .map(|(key, value)| format!("{key} {}", value.load(Ordering::SeqCst)))
+      .collect()
+  }
+
+  /// Number of values recorded by the histograms with the given name.
+  pub fn histograms(&self, name: &str) -> u64 {
+    let histograms = self.histograms.lock().unwrap();
+    histograms
+      .iter()
+      .filter(|(key, _)| key.split('{').next() == Some(name))
+      .map(|(_, count)| count.load(Ordering::SeqCst))
+      .sum()
+  }
+
+  fn entry(values: &Values, key: &Key) -> Arc<AtomicU64> {
+    let labels = key
+      .labels()
+      .map(|label| format!("{}={}", label.key(), label.value()))
+      .collect::<Vec<_>>()
+      .join(",");
+    let name = format!("{}{{{labels}}}", key.name());
+    values.lock().unwrap().entry(name).or_default().clone()
+  }
+}
+
+struct HistogramCount(Arc<AtomicU64>);
+
+impl HistogramFn for HistogramCount {
+  fn record(&self, _value: f64) {
+    self.0.fetch_add(1, Ordering::SeqCst);
+  }
+}
+
+impl Recorder for MetricsRecorder {
+  fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+  fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+  fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+  fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+    Counter::from_arc(MetricsRecorder::entry(&self.counters, key))
+  }
+
+  fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+    Gauge::noop()
+  }
+
+  fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+    let count = MetricsRecorder::entry(&self.histograms, key);
+    Histogram::from_arc(Arc::new(HistogramCount(count)))
+  }
+}
@@ -0,0 +1,284 @@
+use std::time::Instant;
+
+#[cfg(feature = "cookies")]
+use crate::reqwest::utils::set_cookies;
+use crate::reqwest::utils::{self, convert_headers, convert_method, header_map};
+use reqwest::blocking::Client;
+use reqwest::header::CONTENT_TYPE;
+#[cfg(feature = "cookies")]
+use reqwest::header::COOKIE;
+use reqwest::Error;
+
+use crate::blocking::{BaseHttpClient, StreamingResponse};
+#[cfg(any(
+  feature = "reqwest-native-tls",
+  feature = "reqwest-native-tls-vendored",
+  feature = "reqwest-default-tls",
+  feature = "reqwest-rustls-tls"
+))]
+use crate::common::CustomCertHttpClient;
+use crate::common::{HttpError, HttpRequest, HttpResponse};
+use crate::config::{ConfigError, FromConfig, HttpClientConfig};
+#[cfg(feature = "cookies")]
+use crate::cookies::CookieJar;
+use crate::logging::RequestLogger;
+
+/// Blocking client built on top of [`reqwest::blocking`].
+///
+/// It must not be used from within an async runtime, as `reqwest` runs its own runtime
+/// internally to drive the requests.
+#[derive(Debug, Clone)]
+pub struct ReqwestBlockingClient {
+  client: Client,
+  logger: RequestLogger,
+  #[cfg(feature = "cookies")]
+  cookies: Option<CookieJar>,
+}
+
+impl Default for ReqwestBlockingClient {
+  fn default() -> Self {
+    Self::from_config(&HttpClientConfig::new()).expect("Failed to initialize TLS")
+  }
+}
+
+impl FromConfig for ReqwestBlockingClient {
+  fn from_config(config: &HttpClientConfig) -> Result<Self, ConfigError> {
+    let builder = utils::blocking::configure(Client::builder(), config)?;
+    let client = builder
+      .build()
+      .map_err(|err| ConfigError::Client(err.to_string()))?;
+    Ok(Self {
+      client,
+      logger: RequestLogger::default(),
+      #[cfg(feature = "cookies")]
+      cookies: None,
+    })
+  }
+}
+
+impl ReqwestBlockingClient {
+  /// Replaces the logger used for outgoing requests, see [`RequestLogger`].
+  pub fn with_request_logger(mut self, logger: RequestLogger) -> Self {
+    self.logger = logger;
+    self
+  }
+
+  /// Stores the cookies set by the responses in the given jar and sends them back with
+  /// the following requests, see [`CookieJar`].
+  #[cfg(feature = "cookies")]
+  pub fn with_cookie_jar(mut self, jar: CookieJar) -> Self {
+    self.cookies = Some(jar);
+    self
+  }
+
+  /// Sends the request, turning the non-`2xx` responses into status errors.
+  fn exchange(
+    &self,
+    request: &HttpRequest,
+  ) -> Result<reqwest::blocking::Response, HttpError<Error>> {
+    let url = request.url.as_str();
+    let mut builder = self
+      .client
+      .request(convert_method(request.method), url)
+      .headers(header_map(&request.headers))
+      .query(&request.query);
+
+    let (content_type, body) = request.body.encode();
+    if let Some(content_type) =
+      content_type.filter(|_| !request.headers.contains_key("content-type"))
+    {
+      builder = builder.header(CONTENT_TYPE, content_type);
+    }
+    if !request.body.is_empty() {
+      builder = builder.body(body);
+    }
+    if let Some(timeout) = request.timeout {
+      builder = builder.timeout(timeout);
+    }
+
+    #[cfg(feature = "cookies")]
+    if let Some(cookie) = self
+      .cookies
+      .as_ref()
+      .and_then(|jar| jar.request_header(url, Some(&request.headers)))
+    {
+      builder = builder.header(COOKIE, cookie);
+    }
+
+    self.logger.log_request(request);
+    let started = Instant::now();
+    let response = builder.send()?;
+    #[cfg(feature = "cookies")]
+    if let Some(jar) = &self.cookies {
+      jar.store_response(response.url().as_str(), set_cookies(response.headers()));
+    }
+    self.logger.log_response(
+      request.method.as_str(),
+      url,
+      response.status().as_u16(),
+      started.elapsed(),
+    );
+
+    if response.status().is_success() {
+      return Ok(response);
+    }
+    let status = response.status().as_u16();
+    let headers = convert_headers(response.headers());
+    // the status is the actual error, so a body that can't be read is left empty
+    let body = response.bytes().map(|body| body.to_vec());
+    Err(HttpError::from_status(
+      status,
+      headers,
+      body.unwrap_or_default(),
+    ))
+  }
+}
+
+impl BaseHttpClient for ReqwestBlockingClient {
+  type Error = HttpError<Error>;
+
+  fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Self::Error> {
+    let response = self.exchange(request)?;
+    Ok(HttpResponse::new(
+      response.status().as_u16(),
+      convert_headers(response.headers()),
+      response.bytes()?.to_vec(),
+    ))
+  }
+
+  fn send_streaming(&self, request: &HttpRequest) -> Result<StreamingResponse, Self::Error> {
+    let response = self.exchange(request)?;
+    let status = response.status().as_u16();
+    let headers = convert_headers(response.headers());
+    Ok(StreamingResponse::new(status, headers, Box::new(response)))
+  }
+}
+
+#[cfg(any(
+  feature = "reqwest-native-tls",
+  feature = "reqwest-native-tls-vendored",
+  feature = "reqwest-default-tls",
+  feature = "reqwest-rustls-tls"
+))]
+impl CustomCertHttpClient for ReqwestBlockingClient {
+  fn with_custom_cert<CA, CLIENT, KEY>(ca: &CA, client: &CLIENT, key: &KEY) -> Self
+  where
+    CA: AsRef<[u8]>,
+    CLIENT: AsRef<[u8]>,
+    KEY: AsRef<[u8]>,
+  {
+    let config = HttpClientConfig::new().with_custom_cert(ca, client, key);
+    Self::from_config(&config).expect("Failed to initialize TLS")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::ReqwestBlockingClient;
+  use crate::blocking::{BaseHttpClient, StreamingResponse};
+  #[cfg(any(
+    feature = "reqwest-native-tls",
+    feature = "reqwest-native-tls-vendored",
+    feature = "reqwest-default-tls",
+    feature = "reqwest-rustls-tls"
+  ))]
+  use crate::common::{CustomCertHttpClient, HttpError};
+
+  #[cfg(any(
+    feature = "reqwest-native-tls",
+    feature = "reqwest-native-tls-vendored",
+    feature = "reqwest-default-tls",
+    feature = "reqwest-rustls-tls"
+  ))]
+  fn create_response_with_custom_cert() -> Result<(), HttpError<reqwest::Error>> {
+    let client = ReqwestBlockingClient::with_custom_cert(
+      include_bytes!("../../../../resources/ca.pem"),
+      include_bytes!("../../../../resources/client.pem"),
+      include_bytes!("../../../../resources/key.pem"),
+    );
+
+    let response = client.get(
+      "https://events2.gameforge.com/",
+      Default::default(),
+      &Default::default(),
+    )?;
+
+    assert_eq!(response.status(), 200);
+    Ok(())
+  }
+
+  #[test]
+  #[cfg(feature = "reqwest-rustls-tls")]
+  fn create_client_with_custom_cert_rustls_tls() -> Result<(), HttpError<reqwest::Error>> {
+    println!("Testing blocking reqwest with rustls-tls");
+    create_response_with_custom_cert()
+  }
+
+  #[test]
+  #[cfg(any(
+    feature = "reqwest-default-tls",
+    feature = "reqwest-native-tls",
+    feature = "reqwest-native-tls-vendored"
+  ))]
+  fn create_client_with_custom_cert_native_tls() -> Result<(), HttpError<reqwest::Error>> {
+    println!("Testing blocking reqwest with native-tls");
+    create_response_with_custom_cert()
+  }
+
+  #[test]
+  fn sends_any_method_with_query_and_body() {
+    use crate::common::{HttpRequest, Method};
+    use crate::testing::serve_once;
+
+    let (url, server) = serve_once("HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok");
+    let request = HttpRequest::new(Method::Put, format!("{url}/upload?v=1"))
+      .with_query("name", "blob")
+      .with_header("Content-Type", "application/octet-stream")
+      .with_bytes(*b"\x01data");
+    let response = ReqwestBlockingClient::default().send(&request).unwrap();
+    assert_eq!(response.body(), "ok");
+
+    let request = server.join().unwrap();
+    assert!(request.starts_with("PUT /upload?v=1&name=blob HTTP/1.1\r\n"));
+    assert!(request.contains("Content-Type: application/octet-stream\r\n"));
+    assert!(request.ends_with("\r\n\r\n\x01data"));
+  }
+
+  #[test]
+  fn streams_response_bodies() {
+    use std::io::Read;
+
+    use crate::common::{HttpRequest, Method};
+    use crate::testing::serve_once;
+
+    let (url, server) = serve_once(b"HTTP/1.1 200 OK\r\ncontent-length: 3\r\n\r\n\x89P\x00");
+    let request = HttpRequest::new(Method::Get, url);
+    let response: StreamingResponse = ReqwestBlockingClient::default()
+      .send_streaming(&request)
+      .unwrap();
+    let mut body = vec![];
+    response.into_body().read_to_end(&mut body).unwrap();
+    server.join().unwrap();
+    assert_eq!(body, b"\x89P\x00");
+  }
+
+  #[test]
+  #[cfg(all(feature = "gzip", feature = "deflate"))]
+  fn decompresses_responses() {
+    use crate::testing::{compressed_response, serve_once};
+
+    for encoding in ["gzip", "deflate"] {
+      let (url, server) = serve_once(compressed_response(encoding, "decoded"));
+      let response = ReqwestBlockingClient::default()
+        .get(&url, None, &Default::default())
+        .unwrap();
+      let request = server.join().unwrap();
+      let accept_encoding = request
+        .lines()
+        .find_map(|line| line.strip_prefix("Accept-Encoding: "))
+        .unwrap();
+      assert!(accept_encoding.contains("gzip") && accept_encoding.contains("deflate"));
+      assert_eq!(response.body(), "decoded");
+    }
+  }
+}
@@ -1,26 +1,18 @@
-use std::error::Error as StdError;
-
 use crate::common::{Headers, HttpError};
-use crate::reqwest::utils::convert_headers;
-use reqwest::{Error, Response};
+use reqwest::Error;
 
 impl From<Error> for HttpError<Error> {
   fn from(error: Error) -> Self {
+    if error.is_timeout() {
+      return HttpError::Timeout;
+    }
     match error.status() {
       Some(status) => HttpError::Status {
         status: status.as_u16(),
         headers: Headers::new(),
+        body: vec![],
       },
       None => HttpError::Client(error),
     }
   }
 }
-
-impl<T: StdError> From<Response> for HttpError<T> {
-  fn from(response: Response) -> Self {
-    HttpError::Status {
-      status: response.status().as_u16(),
-      headers: convert_headers(response.headers()),
-    }
-  }
-}
@@ -0,0 +1,515 @@
+use std::io;
+use std::time::Instant;
+
+#[cfg(feature = "cookies")]
+use crate::reqwest::utils::set_cookies;
+use crate::reqwest::utils::{self, convert_headers, convert_method, header_map};
+use futures_lite::StreamExt;
+use maybe_async::must_be_async;
+use reqwest::header::CONTENT_TYPE;
+#[cfg(feature = "cookies")]
+use reqwest::header::COOKIE;
+use reqwest::{Client, Error};
+
+#[cfg(any(
+  feature = "reqwest-native-tls",
+  feature = "reqwest-native-tls-vendored",
+  feature = "reqwest-default-tls",
+  feature = "reqwest-rustls-tls"
+))]
+use crate::common::CustomCertHttpClient;
+use crate::common::{HttpError, HttpRequest, HttpResponse};
+use crate::config::{ConfigError, FromConfig, HttpClientConfig};
+#[cfg(feature = "cookies")]
+use crate::cookies::CookieJar;
+use crate::logging::RequestLogger;
+use crate::nonblocking::{BaseHttpClient, StreamingResponse};
+
+#[derive(Debug, Clone)]
+pub struct ReqwestClient {
+  #[allow(dead_code)]
+  client: Client,
+  logger: RequestLogger,
+  #[cfg(feature = "cookies")]
+  cookies: Option<CookieJar>,
+}
+
+impl Default for ReqwestClient {
+  fn default() -> Self {
+    Self::from_config(&HttpClientConfig::new()).expect("Failed to initialize TLS")
+  }
+}
+
+impl FromConfig for ReqwestClient {
+  fn from_config(config: &HttpClientConfig) -> Result<Self, ConfigError> {
+    let builder = utils::nonblocking::configure(Client::builder(), config)?;
+    let client = builder
+      .build()
+      .map_err(|err| ConfigError::Client(err.to_string()))?;
+    Ok(Self {
+      client,
+      logger: RequestLogger::default(),
+      #[cfg(feature = "cookies")]
+      cookies: None,
+    })
+  }
+}
+
+impl ReqwestClient {
+  /// Replaces the logger used for outgoing requests, see [`RequestLogger`].
+  pub fn with_request_logger(mut self, logger: RequestLogger) -> Self {
+    self.logger = logger;
+    self
+  }
+
+  /// Stores the cookies set by the responses in the given jar and sends them back with
+  /// the following requests, see [`CookieJar`].
+  #[cfg(feature = "cookies")]
+  pub fn with_cookie_jar(mut self, jar: CookieJar) -> Self {
+    self.cookies = Some(jar);
+    self
+  }
+
+  /// Sends the request, turning the non-`2xx` responses into status errors.
+  async fn exchange(&self, request: &HttpRequest) -> Result<reqwest::Response, HttpError<Error>> {
+    let url = request.url.as_str();
+    let mut builder = self
+      .client
+      .request(convert_method(request.method), url)
+      .headers(header_map(&request.headers))
+      .query(&request.query);
+
+    let (content_type, body) = request.body.encode();
+    if let Some(content_type) =
+      content_type.filter(|_| !request.headers.contains_key("content-type"))
+    {
+      builder = builder.header(CONTENT_TYPE, content_type);
+    }
+    if !request.body.is_empty() {
+      builder = builder.body(body);
+    }
+    if let Some(timeout) = request.timeout {
+      builder = builder.timeout(timeout);
+    }
+
+    #[cfg(feature = "cookies")]
+    if let Some(cookie) = self
+      .cookies
+      .as_ref()
+      .and_then(|jar| jar.request_header(url, Some(&request.headers)))
+    {
+      builder = builder.header(COOKIE, cookie);
+    }
+
+    self.logger.log_request(request);
+    let started = Instant::now();
+    let response = builder.send().await?;
+    #[cfg(feature = "cookies")]
+    if let Some(jar) = &self.cookies {
+      jar.store_response(response.url().as_str(), set_cookies(response.headers()));
+    }
+    self.logger.log_response(
+      request.method.as_str(),
+      url,
+      response.status().as_u16(),
+      started.elapsed(),
+    );
+
+    if response.status().is_success() {
+      return Ok(response);
+    }
+    let status = response.status().as_u16();
+    let headers = convert_headers(response.headers());
+    // the status is the actual error, so a body that can't be read is left empty
+    let body = response.bytes().await.map(|body| body.to_vec());
+    Err(HttpError::from_status(
+      status,
+      headers,
+      body.unwrap_or_default(),
+    ))
+  }
+}
+
+#[must_be_async]
+impl BaseHttpClient for ReqwestClient {
+  type Error = HttpError<Error>;
+
+  async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Self::Error> {
+    let response = self.exchange(request).await?;
+    Ok(HttpResponse::new(
+      response.status().as_u16(),
+      convert_headers(response.headers()),
+      response.bytes().await?.to_vec(),
+    ))
+  }
+
+  async fn send_streaming(&self, request: &HttpRequest) -> Result<StreamingResponse, Self::Error> {
+    let response = self.exchange(request).await?;
+    let status = response.status().as_u16();
+    let headers = convert_headers(response.headers());
+    let body = response
+      .bytes_stream()
+      .map(|chunk| chunk.map(|chunk| chunk.to_vec()).map_err(io::Error::other));
+    Ok(StreamingResponse::new(status, headers, Box::pin(body)))
+  }
+}
+
+#[cfg(any(
+  feature = "reqwest-native-tls",
+  feature = "reqwest-native-tls-vendored",
+  feature = "reqwest-default-tls",
+  feature = "reqwest-rustls-tls"
+))]
+impl CustomCertHttpClient for ReqwestClient {
+  fn with_custom_cert<CA, CLIENT, KEY>(ca: &CA, client: &CLIENT, key: &KEY) -> Self
+  where
+    CA: AsRef<[u8]>,
+    CLIENT: AsRef<[u8]>,
+    KEY: AsRef<[u8]>,
+  {
+    let config = HttpClientConfig::new().with_custom_cert(ca, client, key);
+    Self::from_config(&config).expect("Failed to initialize TLS")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  #[cfg(any(
+    feature = "reqwest-native-tls",
+    feature = "reqwest-native-tls-vendored",
+    feature = "reqwest-default-tls",
+    feature = "reqwest-rustls-tls"
+  ))]
+  use crate::common::CustomCertHttpClient;
+  use crate::common::HttpError;
+  use crate::nonblocking::{BaseHttpClient, HttpClient};
+
+  #[cfg(any(
+    feature = "reqwest-native-tls",
+    feature = "reqwest-native-tls-vendored",
+    feature = "reqwest-default-tls",
+    feature = "reqwest-rustls-tls"
+  ))]
+  async fn create_response_with_custom_cert() -> Result<(), HttpError<reqwest::Error>> {
+    let client = HttpClient::with_custom_cert(
+      include_bytes!("../../../../resources/ca.pem"),
+      include_bytes!("../../../../resources/client.pem"),
+      include_bytes!("../../../../resources/key.pem"),
+    );
+
+    let response = client
+      .get(
+        "https://events2.gameforge.com/",
+        Default::default(),
+        &Default::default(),
+      )
+      .await?;
+
+    assert_eq!(response.status(), 200);
+    Ok(())
+  }
+
+  #[tokio::test]
+  #[cfg(feature = "cookies")]
+  async fn keeps_cookies_between_requests() {
+    use crate::cookies::CookieJar;
+    use crate::reqwest::ReqwestClient;
+    use crate::testing::serve_once;
+
+    let jar = CookieJar::new();
+    let client = ReqwestClient::default().with_cookie_jar(jar.clone());
+
+    let (url, server) = serve_once(
+      "HTTP/1.1 404 Not Found\r\nset-cookie: a=1\r\nset-cookie: b=2\r\ncontent-length: 0\r\n\r\n",
+    );
+    assert!(client.get(&url, None, &Default::default()).await.is_err());
+    server.join().unwrap();
+    assert_eq!(jar.get(&url, "a"), Some("1".to_string()));
+
+    let (url, server) = serve_once("HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n");
+    client.get(&url, None, &Default::default()).await.unwrap();
+    let request = server.join().unwrap();
+    assert!(request.contains("Cookie: a=1; b=2\r\n") || request.contains("Cookie: b=2; a=1\r\n"));
+  }
+
+  #[tokio::test]
+  async fn sends_requests_through_configured_proxy() {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use crate::config::{FromConfig, HttpClientConfig, Proxy};
+    use crate::reqwest::ReqwestClient;
+    use crate::testing::serve_once;
+
+    let (url, proxy) = serve_once("HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok");
+    let config = HttpClientConfig::new()
+      .with_proxy(Proxy::new(&url).unwrap().with_auth("user", "secret"))
+      .with_local_address(IpAddr::V4(Ipv4Addr::LOCALHOST));
+    let client = ReqwestClient::from_config(&config).unwrap();
+
+    let response = client
+      .get("http://gameforge.invalid/api", None, &Default::default())
+      .await
+      .unwrap();
+    assert_eq!(response.body(), "ok");
+
+    let request = proxy.join().unwrap().to_lowercase();
+    assert!(request.starts_with("get http://gameforge.invalid/api http/1.1\r\n"));
+    assert!(request.contains("proxy-authorization: basic dxnlcjpzzwnyzxq=\r\n"));
+  }
+
+  #[tokio::test]
+  async fn connects_to_overridden_addresses() {
+    use crate::config::{FromConfig, HttpClientConfig};
+    use crate::reqwest::ReqwestClient;
+    use crate::testing::serve_once;
+
+    let (url, server) = serve_once("HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok");
+    let address = url.trim_start_matches("http://").parse().unwrap();
+    let config = HttpClientConfig::new().with_resolve("spark.gameforge.com", [address]);
+    let client = ReqwestClient::from_config(&config).unwrap();
+
+    let response = client
+      .get("http://spark.gameforge.com/api", None, &Default::default())
+      .await
+      .unwrap();
+    assert_eq!(response.body(), "ok");
+    let request = server.join().unwrap();
+    assert!(request.contains("Host: spark.gameforge.com\r\n"));
+  }
+
+  #[tokio::test]
+  async fn times_out_requests() {
+    use std::time::Duration;
+
+    use crate::common::{HttpRequest, Method};
+    use crate::reqwest::ReqwestClient;
+    use crate::testing::serve_never;
+
+    let (url, _listener) = serve_never();
+    let request = HttpRequest::new(Method::Get, url).with_timeout(Duration::from_millis(100));
+
+    let result = ReqwestClient::default().send(&request).await;
+    assert!(matches!(result, Err(HttpError::Timeout)));
+  }
+
+  #[tokio::test]
+  #[cfg(feature = "cookies")]
+  async fn cancelled_requests_leave_cookies_untouched() {
+    use std::time::Duration;
+
+    use crate::common::Query;
+    use crate::cookies::CookieJar;
+    use crate::reqwest::ReqwestClient;
+    use crate::testing::{serve_never, serve_once};
+
+    let (url, _listener) = serve_never();
+    let jar = CookieJar::new();
+    jar.insert(&url, "session=abc; Path=/");
+    let client = ReqwestClient::default().with_cookie_jar(jar.clone());
+
+    let query = Query::new();
+    let request = client.get(&url, None, &query);
+    assert!(tokio::time::timeout(Duration::from_millis(100), request)
+      .await
+      .is_err());
+    assert_eq!(jar.get(&url, "session"), Some("abc".to_string()));
+
+    let (url, server) =
+      serve_once("HTTP/1.1 200 OK\r\nset-cookie: session=def\r\ncontent-length: 0\r\n\r\n");
+    jar.insert(&url, "session=abc; Path=/");
+    client.get(&url, None, &Default::default()).await.unwrap();
+    assert!(server.join().unwrap().contains("Cookie: session=abc\r\n"));
+    assert_eq!(jar.get(&url, "session"), Some("def".to_string()));
+  }
+
+  #[tokio::test]
+  #[cfg(feature = "http2")]
+  async fn sends_http2_with_prior_knowledge() {
+    use crate::config::{ConnectionConfig, FromConfig, HttpClientConfig, HttpVersion};
+    use crate::reqwest::ReqwestClient;
+    use crate::testing::accept_preface;
+
+    let (url, server) = accept_preface();
+    let connection = ConnectionConfig::new()
+      .with_http_version(HttpVersion::Http2PriorKnowledge)
+      .with_max_idle_per_host(4)
+      .with_tcp_keepalive(std::time::Duration::from_secs(30));
+    let config = HttpClientConfig::new().with_connection(connection);
+    let client = ReqwestClient::from_config(&config).unwrap();
+
+    // the server closes the connection once the preface is read
+    let _ = client.get(&url, None, &Default::default()).await;
+    assert_eq!(server.join().unwrap(), b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n");
+  }
+
+  #[tokio::test]
+  #[cfg(all(feature = "pinning", feature = "__reqwest-native-tls"))]
+  async fn rejects_servers_without_pinned_keys_with_native_tls() {
+    use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+
+    use crate::config::{FromConfig, HttpClientConfig};
+    use crate::pinning::Pin;
+    use crate::reqwest::ReqwestClient;
+    use crate::testing::serve_tls;
+
+    let mut ca_params = CertificateParams::new(Vec::new()).unwrap();
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    // OpenSSL takes a certificate with the name of its issuer as self-signed
+    ca_params
+      .distinguished_name
+      .push(DnType::CommonName, "gf-auth CA");
+    let ca_key = KeyPair::generate().unwrap();
+    let ca = ca_params.self_signed(&ca_key).unwrap();
+    let key = KeyPair::generate().unwrap();
+    let leaf = CertificateParams::new(vec!["127.0.0.1".to_string()])
+      .unwrap()
+      .signed_by(&key, &ca, &ca_key)
+      .unwrap();
+    let (url, server) = serve_tls(&[&leaf], &key, 3);
+
+    let pin = |cert: &rcgen::Certificate| Pin::from_certificate(cert.der()).unwrap();
+    let request = |pin: Pin| {
+      let config = HttpClientConfig::new()
+        .with_custom_cert(&ca.pem(), &leaf.pem(), &key.serialize_pem())
+        .with_pins("127.0.0.1", [pin]);
+      let url = url.clone();
+      async move {
+        ReqwestClient::from_config(&config)
+          .unwrap()
+          .get(&url, None, &Default::default())
+          .await
+      }
+    };
+
+    assert_eq!(request(pin(&leaf)).await.unwrap().status(), 200);
+    // native-tls only gives the certificate of the server
+    for pinned in [pin(&ca), Pin::from_sha256([0; 32])] {
+      let err = request(pinned).await.unwrap_err();
+      assert_eq!(err.pin_mismatch().unwrap().host, "127.0.0.1");
+    }
+    server.join().unwrap();
+  }
+
+  #[tokio::test]
+  #[cfg(feature = "reqwest-rustls-tls")]
+  async fn create_client_with_custom_cert_rustls_tls() -> Result<(), HttpError<reqwest::Error>> {
+    println!("Testing reqwest with rustls-tls");
+    create_response_with_custom_cert().await
+  }
+
+  #[tokio::test]
+  #[cfg(any(
+    feature = "reqwest-default-tls",
+    feature = "reqwest-native-tls",
+    feature = "reqwest-native-tls-vendored"
+  ))]
+  async fn create_client_with_custom_cert_native_tls() -> Result<(), HttpError<reqwest::Error>> {
+    println!("Testing reqwest with native-tls");
+    create_response_with_custom_cert().await
+  }
+
+  #[tokio::test]
+  async fn reads_binary_and_streamed_bodies() {
+    use futures_lite::StreamExt;
+
+    use crate::common::{HttpRequest, Method};
+    use crate::testing::serve_once;
+
+    let (url, server) = serve_once(b"HTTP/1.1 200 OK\r\ncontent-length: 3\r\n\r\n\x89P\x00");
+    let response = HttpClient::default()
+      .get(&url, None, &Default::default())
+      .await
+      .unwrap();
+    server.join().unwrap();
+    assert_eq!(response.bytes(), b"\x89P\x00");
+
+    let (url, server) = serve_once("HTTP/1.1 200 OK\r\ncontent-length: 6\r\n\r\nstream");
+    let request = HttpRequest::new(Method::Get, url);
+    let response = HttpClient::default()
+      .send_streaming(&request)
+      .await
+      .unwrap();
+    let chunks = response
+      .into_body()
+      .try_collect::<_, _, Vec<_>>()
+      .await
+      .unwrap();
+    server.join().unwrap();
+    assert_eq!(chunks.concat(), b"stream");
+  }
+
+  #[tokio::test]
+  async fn keeps_repeated_response_headers() {
+    use crate::testing::serve_once;
+
+    let (url, server) = serve_once(
+      "HTTP/1.1 200 OK\r\nSet-Cookie: a=1\r\nX-Test: yes\r\nset-cookie: b=2\r\ncontent-length: 0\r\n\r\n",
+    );
+    let response = HttpClient::default()
+      .get(&url, None, &Default::default())
+      .await
+      .unwrap();
+    server.join().unwrap();
+
+    let headers = response.headers();
+    assert_eq!(
+      headers.get_all("Set-Cookie").collect::<Vec<_>>(),
+      ["a=1", "b=2"]
+    );
+    assert_eq!(headers.get("x-test"), Some("yes"));
+  }
+
+  #[tokio::test]
+  async fn sends_headers_in_order() {
+    use crate::common::{HttpRequest, Method};
+    use crate::launcher::LauncherProfile;
+    use crate::middleware::MiddlewareClient;
+    use crate::testing::serve_once;
+
+    let (url, server) = serve_once("HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n");
+    let client = MiddlewareClient::new(HttpClient::default())
+      .with(LauncherProfile::new("Launcher/1.0", "installation"));
+    let request = HttpRequest::new(Method::Get, url).with_header("X-Trace", "a");
+    client.send(&request).await.unwrap();
+
+    let request = server.join().unwrap();
+    let headers = request
+      .lines()
+      .skip(1)
+      .take_while(|line| !line.is_empty())
+      .filter(|line| !line.starts_with("Host: ") && !line.starts_with("Accept-Encoding: "))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      headers,
+      [
+        "User-Agent: Launcher/1.0",
+        "Tnt-Installation-Id: installation",
+        "Origin: spark://www.gameforge.com",
+        "Accept: */*",
+        "Accept-Language: en-US,en;q=0.9",
+        "X-Trace: a",
+      ]
+    );
+  }
+
+  #[tokio::test]
+  #[cfg(all(feature = "gzip", feature = "deflate"))]
+  async fn decompresses_responses() {
+    use crate::testing::{compressed_response, serve_once};
+
+    for encoding in ["gzip", "deflate"] {
+      let (url, server) = serve_once(compressed_response(encoding, "decoded"));
+      let response = HttpClient::default()
+        .get(&url, None, &Default::default())
+        .await
+        .unwrap();
+      let request = server.join().unwrap();
+      let accept_encoding = request
+        .lines()
+        .find_map(|line| line.strip_prefix("Accept-Encoding: "))
+        .unwrap();
+      assert!(accept_encoding.contains("gzip") && accept_encoding.contains("deflate"));
+      assert_eq!(response.body(), "decoded");
+    }
+  }
+}
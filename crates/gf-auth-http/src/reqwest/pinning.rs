@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::future::Future;
+use std::pin::Pin as PinBox;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use http::Extensions;
+use hyper_util::client::legacy::connect::Connection;
+use reqwest::tls::TlsInfo;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::pinning::{verify_certificate_pins, Pin};
+
+/// Checks the pinned keys against the certificate of the server once native-tls verified
+/// it, before the connection is used for any request.
+///
+/// reqwest hides the host the connection is made to, so the key is checked against the
+/// pins of every host the certificate is valid for.
+#[derive(Debug, Clone)]
+pub(crate) struct PinningLayer {
+  pub pins: Arc<HashMap<String, Vec<Pin>>>,
+}
+
+impl<S> Layer<S> for PinningLayer {
+  type Service = PinningConnector<S>;
+
+  fn layer(&self, inner: S) -> Self::Service {
+    PinningConnector {
+      inner,
+      pins: self.pins.clone(),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct PinningConnector<S> {
+  inner: S,
+  pins: Arc<HashMap<String, Vec<Pin>>>,
+}
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+impl<S, R> Service<R> for PinningConnector<S>
+where
+  S: Service<R, Error = BoxError>,
+  S::Response: Connection + Send + 'static,
+  S::Future: Send + 'static,
+{
+  type Response = S::Response;
+  type Error = BoxError;
+  type Future = PinBox<Box<dyn Future<Output = Result<S::Response, BoxError>> + Send>>;
+
+  fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+    self.inner.poll_ready(cx)
+  }
+
+  fn call(&mut self, request: R) -> Self::Future {
+    let connecting = self.inner.call(request);
+    let pins = self.pins.clone();
+    Box::pin(async move {
+      let connection = connecting.await?;
+      let mut extras = Extensions::new();
+      connection.connected().get_extras(&mut extras);
+      // plain HTTP connections have no certificate to check
+      if let Some(certificate) = extras.get::<TlsInfo>().and_then(TlsInfo::peer_certificate) {
+        verify_certificate_pins(&pins, certificate)?;
+      }
+      Ok(connection)
+    })
+  }
+}
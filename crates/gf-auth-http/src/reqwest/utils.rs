@@ -1,5 +1,19 @@
-use crate::common::Headers;
-use reqwest::header::HeaderMap;
+use crate::common::{Headers, Method};
+#[cfg(any(
+  feature = "reqwest-native-tls",
+  feature = "reqwest-native-tls-vendored",
+  feature = "reqwest-default-tls",
+  feature = "reqwest-rustls-tls"
+))]
+use crate::config::CustomCert;
+#[cfg(all(feature = "pinning", feature = "reqwest-rustls-tls"))]
+use crate::config::HttpClientConfig;
+use crate::config::{ConfigError, Proxy};
+#[cfg(all(feature = "pinning", feature = "reqwest-rustls-tls"))]
+use crate::pinning::with_pinned_roots;
+#[cfg(feature = "cookies")]
+use reqwest::header::SET_COOKIE;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 
 pub(crate) fn convert_headers(raw: &HeaderMap) -> Headers {
   raw
@@ -17,3 +31,222 @@ pub(crate) fn convert_headers(raw: &HeaderMap) -> Headers {
     })
     .collect()
 }
+
+/// Converts the headers of a request, leaving out the invalid ones.
+pub(crate) fn header_map(headers: &Headers) -> HeaderMap {
+  let mut map = HeaderMap::with_capacity(headers.len());
+  for (name, value) in headers {
+    match (HeaderName::try_from(name), HeaderValue::try_from(value)) {
+      (Ok(name), Ok(value)) => {
+        map.append(name, value);
+      }
+      _ => log::error!("invalid header not sent: {name}"),
+    }
+  }
+  map
+}
+
+/// Values of the `Set-Cookie` headers, which can be sent more than once.
+#[cfg(feature = "cookies")]
+pub(crate) fn set_cookies(raw: &HeaderMap) -> impl Iterator<Item = &str> {
+  raw
+    .get_all(SET_COOKIE)
+    .iter()
+    .filter_map(|value| value.to_str().ok())
+}
+
+pub(crate) fn convert_method(method: Method) -> reqwest::Method {
+  match method {
+    Method::Get => reqwest::Method::GET,
+    Method::Head => reqwest::Method::HEAD,
+    Method::Post => reqwest::Method::POST,
+    Method::Put => reqwest::Method::PUT,
+    Method::Patch => reqwest::Method::PATCH,
+    Method::Delete => reqwest::Method::DELETE,
+    Method::Options => reqwest::Method::OPTIONS,
+  }
+}
+
+pub(crate) fn proxy(proxy: &Proxy) -> Result<reqwest::Proxy, ConfigError> {
+  reqwest::Proxy::all(proxy.as_str()).map_err(|err| ConfigError::Proxy(err.to_string()))
+}
+
+/// Client identity and the CA root certificate of a custom certificate.
+#[cfg(any(
+  feature = "reqwest-native-tls",
+  feature = "reqwest-native-tls-vendored",
+  feature = "reqwest-default-tls",
+  feature = "reqwest-rustls-tls"
+))]
+pub(crate) fn custom_cert(
+  cert: &CustomCert,
+) -> Result<(reqwest::Identity, reqwest::Certificate), ConfigError> {
+  #[cfg(not(feature = "reqwest-rustls-tls"))]
+  let identity = reqwest::Identity::from_pkcs8_pem(&cert.client, &cert.key);
+  #[cfg(feature = "reqwest-rustls-tls")]
+  let identity = {
+    let mut pem = vec![];
+    pem.extend_from_slice(&cert.key);
+    pem.extend_from_slice(&cert.client);
+    reqwest::Identity::from_pem(&pem)
+  };
+  let identity = identity.map_err(|err| ConfigError::Tls(format!("client certificate: {err}")))?;
+
+  let root = reqwest::Certificate::from_pem(&cert.ca)
+    .map_err(|err| ConfigError::Tls(format!("CA root certificate: {err}")))?;
+  Ok((identity, root))
+}
+
+/// rustls configuration checking the pinned keys, trusting the webpki roots like reqwest
+/// along with the CA of the custom certificate.
+#[cfg(all(feature = "pinning", feature = "reqwest-rustls-tls"))]
+pub(crate) fn pinned_tls_config(
+  config: &HttpClientConfig,
+) -> Result<rustls::ClientConfig, ConfigError> {
+  use std::io::Cursor;
+  use std::sync::Arc;
+
+  let mut root_store =
+    rustls::RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+  if let Some(cert) = &config.custom_cert {
+    root_store
+      .add_parsable_certificates(rustls_pemfile::certs(&mut Cursor::new(&cert.ca)).flatten());
+  }
+
+  let builder =
+    rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+      .with_safe_default_protocol_versions()
+      // the default protocol versions are supported by the ring provider
+      .unwrap();
+  let builder = with_pinned_roots(builder, root_store, &config.pins)?;
+
+  let Some(cert) = &config.custom_cert else {
+    return Ok(builder.with_no_client_auth());
+  };
+  let private_key = rustls_pemfile::private_key(&mut Cursor::new(&cert.key))
+    .and_then(|item| item.ok_or(std::io::Error::from(std::io::ErrorKind::UnexpectedEof)))
+    .map_err(|err| ConfigError::Tls(format!("private key: {err}")))?;
+  let client_certs = rustls_pemfile::certs(&mut Cursor::new(&cert.client))
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|err| ConfigError::Tls(format!("client certificate: {err}")))?;
+  builder
+    .with_client_auth_cert(client_certs, private_key)
+    .map_err(|err| ConfigError::Tls(err.to_string()))
+}
+
+/// Defines `configure`, which applies a [`HttpClientConfig`] to a client builder. The
+/// async and blocking builders have the same methods, but no trait to share the code.
+macro_rules! configure_builder {
+  ($builder:ty) => {
+    pub(crate) fn configure(
+      builder: $builder,
+      config: &crate::config::HttpClientConfig,
+    ) -> Result<$builder, crate::config::ConfigError> {
+      use crate::config::HttpVersion;
+
+      let mut builder = builder
+        .local_address(config.local_address)
+        .http1_title_case_headers();
+      if let Some(proxy) = &config.proxy {
+        builder = builder.proxy(super::proxy(proxy)?);
+      }
+      for (host, addresses) in &config.resolve {
+        builder = builder.resolve_to_addrs(host, addresses);
+      }
+      if let Some(timeout) = config.timeout {
+        builder = builder.timeout(timeout);
+      }
+
+      let connection = &config.connection;
+      if let Some(timeout) = connection.idle_timeout {
+        builder = builder.pool_idle_timeout(timeout);
+      }
+      if let Some(max) = connection.max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max);
+      }
+      if let Some(keepalive) = connection.tcp_keepalive {
+        builder = builder.tcp_keepalive(keepalive);
+      }
+      if let Some(nodelay) = connection.tcp_nodelay {
+        builder = builder.tcp_nodelay(nodelay);
+      }
+      builder = match connection.http_version {
+        HttpVersion::Negotiated => builder,
+        HttpVersion::Http1Only => builder.http1_only(),
+        #[cfg(feature = "http2")]
+        HttpVersion::Http2PriorKnowledge => builder.http2_prior_knowledge(),
+        #[cfg(not(feature = "http2"))]
+        HttpVersion::Http2PriorKnowledge => {
+          return Err(crate::config::ConfigError::Unsupported(
+            "HTTP/2 without the `http2` feature",
+          ))
+        }
+      };
+
+      #[cfg(any(
+        feature = "reqwest-native-tls",
+        feature = "reqwest-native-tls-vendored",
+        feature = "reqwest-default-tls"
+      ))]
+      if let Some(cert) = &config.custom_cert {
+        let (identity, root) = super::custom_cert(cert)?;
+        builder = builder
+          .identity(identity)
+          .add_root_certificate(root)
+          .use_native_tls();
+      }
+      #[cfg(feature = "reqwest-rustls-tls")]
+      if let Some(cert) = &config.custom_cert {
+        let (identity, root) = super::custom_cert(cert)?;
+        builder = builder
+          .identity(identity)
+          .add_root_certificate(root)
+          .use_rustls_tls();
+      }
+      #[cfg(not(any(
+        feature = "reqwest-native-tls",
+        feature = "reqwest-native-tls-vendored",
+        feature = "reqwest-default-tls",
+        feature = "reqwest-rustls-tls"
+      )))]
+      if config.custom_cert.is_some() {
+        return Err(crate::config::ConfigError::Unsupported(
+          "custom certificate",
+        ));
+      }
+      #[cfg(all(feature = "pinning", feature = "reqwest-rustls-tls"))]
+      if !config.pins.is_empty() {
+        builder = builder.use_preconfigured_tls(super::pinned_tls_config(config)?);
+      }
+      #[cfg(all(feature = "pinning", feature = "__reqwest-native-tls"))]
+      if !config.pins.is_empty() {
+        let pins = std::sync::Arc::new(config.pins.clone());
+        builder = builder
+          .tls_info(true)
+          .connector_layer(crate::reqwest::pinning::PinningLayer { pins });
+      }
+      // without TLS there is no certificate to check
+      #[cfg(all(
+        feature = "pinning",
+        not(any(feature = "__reqwest-native-tls", feature = "reqwest-rustls-tls"))
+      ))]
+      if !config.pins.is_empty() {
+        return Err(crate::config::ConfigError::Unsupported(
+          "certificate pinning",
+        ));
+      }
+
+      Ok(builder)
+    }
+  };
+}
+
+#[cfg(feature = "client-reqwest")]
+pub(crate) mod nonblocking {
+  configure_builder!(reqwest::ClientBuilder);
+}
+
+#[cfg(feature = "client-reqwest-blocking")]
+pub(crate) mod blocking {
+  configure_builder!(reqwest::blocking::ClientBuilder);
+}
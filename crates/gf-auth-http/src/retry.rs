@@ -0,0 +1,340 @@
+use std::error::Error;
+use std::io;
+use std::time::{Duration, SystemTime};
+
+use rand::Rng;
+
+use crate::common::{Headers, HttpError, HttpRequest, HttpResponse};
+
+/// Controls when and how often a [`RetryClient`] retries a failed request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+  /// Maximum number of retries made after the initial attempt.
+  pub max_retries: u32,
+  /// Delay before the first retry, doubled on every following attempt.
+  pub base_delay: Duration,
+  /// Upper bound for a single backoff delay. A request whose `Retry-After` or rate limit
+  /// asks to wait longer than this isn't retried, and its error is returned instead.
+  pub max_delay: Duration,
+  /// Whether non-idempotent `POST` and `PATCH` requests should be retried as well.
+  pub retry_post: bool,
+}
+
+impl RetryPolicy {
+  pub fn new(max_retries: u32, base_delay: Duration, max_delay: Duration) -> Self {
+    RetryPolicy {
+      max_retries,
+      base_delay,
+      max_delay,
+      retry_post: false,
+    }
+  }
+
+  pub fn with_retry_post(mut self, retry_post: bool) -> Self {
+    self.retry_post = retry_post;
+    self
+  }
+
+  fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 429 | 500 | 502 | 503 | 504)
+  }
+
+  fn backoff(&self, attempt: u32) -> Duration {
+    let delay = self
+      .base_delay
+      .saturating_mul(2u32.saturating_pow(attempt))
+      .min(self.max_delay);
+
+    // "Equal jitter": keep half of the delay and randomize the other half, so that
+    // many clients failing at the same time don't retry in lockstep.
+    let half = delay / 2;
+    let jitter = rand::thread_rng().gen_range(Duration::ZERO..=half);
+    half + jitter
+  }
+
+  fn next_delay<E: Error + 'static>(
+    &self,
+    idempotent: bool,
+    attempt: u32,
+    error: &HttpError<E>,
+  ) -> Option<Duration> {
+    if attempt >= self.max_retries || !(idempotent || self.retry_post) {
+      return None;
+    }
+
+    match error {
+      HttpError::Status {
+        status, headers, ..
+      } => {
+        if !Self::is_retryable_status(*status) {
+          return None;
+        }
+
+        match retry_after(headers) {
+          Some(delay) if delay > self.max_delay => None,
+          Some(delay) => Some(delay),
+          None => Some(self.backoff(attempt)),
+        }
+      }
+      HttpError::RateLimited { retry_after } if *retry_after > self.max_delay => None,
+      HttpError::RateLimited { retry_after } => Some(*retry_after),
+      HttpError::Client(error) if is_transport_error(error) => Some(self.backoff(attempt)),
+      HttpError::IO(error) if is_transient(error) => Some(self.backoff(attempt)),
+      HttpError::Timeout => Some(self.backoff(attempt)),
+      HttpError::Client(_) | HttpError::IO(_) => None,
+    }
+  }
+}
+
+/// Whether the error of the client was caused by the connection, the only client errors
+/// which may go away by sending the request again.
+fn is_transport_error(error: &(dyn Error + 'static)) -> bool {
+  let mut next = Some(error);
+  while let Some(error) = next {
+    if let Some(error) = error.downcast_ref::<io::Error>() {
+      return is_transient(error);
+    }
+    next = error.source();
+  }
+  false
+}
+
+/// Invalid data, such as a rejected certificate or a corrupted body, fails the same way
+/// on every attempt.
+fn is_transient(error: &io::Error) -> bool {
+  !matches!(
+    error.kind(),
+    io::ErrorKind::InvalidData | io::ErrorKind::InvalidInput | io::ErrorKind::Unsupported
+  )
+}
+
+impl Default for RetryPolicy {
+  fn default() -> Self {
+    RetryPolicy::new(3, Duration::from_millis(500), Duration::from_secs(30))
+  }
+}
+
+/// Parses the `Retry-After` header, which is either a number of seconds or an HTTP date.
+fn retry_after(headers: &Headers) -> Option<Duration> {
+  let value = headers.get("retry-after")?.trim();
+
+  if let Ok(seconds) = value.parse::<u64>() {
+    return Some(Duration::from_secs(seconds));
+  }
+
+  let date = httpdate::parse_http_date(value).ok()?;
+  Some(
+    date
+      .duration_since(SystemTime::now())
+      .unwrap_or(Duration::ZERO),
+  )
+}
+
+/// A `BaseHttpClient` wrapper which retries requests that failed with a transient
+/// error (`429`, `5xx`, a connection error or a timeout) using exponential backoff with
+/// jitter.
+///
+/// Only idempotent methods are retried by default, `POST` and `PATCH` requests have to
+/// be enabled explicitly with [`RetryPolicy::retry_post`].
+#[derive(Debug, Clone, Default)]
+pub struct RetryClient<C> {
+  inner: C,
+  policy: RetryPolicy,
+}
+
+impl<C> RetryClient<C> {
+  pub fn new(inner: C, policy: RetryPolicy) -> Self {
+    RetryClient { inner, policy }
+  }
+
+  pub fn inner(&self) -> &C {
+    &self.inner
+  }
+
+  pub fn policy(&self) -> &RetryPolicy {
+    &self.policy
+  }
+}
+
+flavoured! {
+  #[maybe_async]
+  async fn execute<F, Fut, T, E>(
+    policy: &RetryPolicy,
+    idempotent: bool,
+    send: F,
+  ) -> Result<T, HttpError<E>>
+  where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = Result<T, HttpError<E>>>,
+    E: Error + 'static,
+  {
+    let mut attempt = 0;
+    loop {
+      let error = match send().await {
+        Ok(response) => return Ok(response),
+        Err(error) => error,
+      };
+
+      match policy.next_delay(idempotent, attempt, &error) {
+        Some(delay) => {
+          log::warn!("Request failed ({error}), retrying in {delay:?}");
+          drop(error);
+          sleep(delay).await;
+          attempt += 1;
+        }
+        None => return Err(error),
+      }
+    }
+  }
+
+  #[maybe_async]
+  impl<C, E> BaseHttpClient for RetryClient<C>
+  where
+    C: BaseHttpClient<Error = HttpError<E>> + Sync,
+    E: Error + Send + 'static,
+  {
+    type Error = C::Error;
+
+    async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Self::Error> {
+      let idempotent = request.method.is_idempotent();
+      execute(&self.policy, idempotent, || self.inner.send(request)).await
+    }
+
+    async fn send_streaming(&self, request: &HttpRequest) -> Result<StreamingResponse, Self::Error> {
+      let idempotent = request.method.is_idempotent();
+      execute(&self.policy, idempotent, || self.inner.send_streaming(request)).await
+    }
+  }
+
+  #[cfg(test)]
+  mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::common::{HttpError, HttpRequest, Method, Query};
+    use crate::retry::{RetryClient, RetryPolicy};
+    use crate::testing::ScriptedClient;
+
+    fn fast_policy() -> RetryPolicy {
+      RetryPolicy::new(3, Duration::from_millis(1), Duration::from_millis(10))
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn retries_transient_statuses() {
+      let inner = ScriptedClient::new(&[Err(503), Err(429), Ok(200)]);
+      let client = RetryClient::new(inner.clone(), fast_policy());
+
+      let response = client.get("", None, &Query::new()).await.unwrap();
+      assert_eq!(response.status(), 200);
+      assert_eq!(inner.calls(), 3);
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn retries_streaming_requests() {
+      let inner = ScriptedClient::new(&[Err(503), Ok(200)]);
+      let client = RetryClient::new(inner.clone(), fast_policy());
+
+      let request = HttpRequest::new(Method::Get, "");
+      let response = client.send_streaming(&request).await.unwrap();
+      assert_eq!(response.status(), 200);
+      assert_eq!(inner.streamed_calls(), 2);
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn gives_up_after_max_retries() {
+      let inner = ScriptedClient::new(&[Err(500), Err(500), Err(500), Err(500), Err(500)]);
+      let client = RetryClient::new(inner.clone(), fast_policy());
+
+      let result = client.get("", None, &Query::new()).await;
+      assert!(matches!(result, Err(HttpError::Status { status: 500, .. })));
+      assert_eq!(inner.calls(), 4);
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn does_not_retry_client_errors() {
+      let inner = ScriptedClient::new(&[Err(404)]);
+      let client = RetryClient::new(inner.clone(), fast_policy());
+
+      let result = client.get("", None, &Query::new()).await;
+      assert!(result.is_err());
+      assert_eq!(inner.calls(), 1);
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn retries_post_only_when_enabled() {
+      let inner = ScriptedClient::new(&[Err(503), Err(503)]);
+      let client = RetryClient::new(inner.clone(), fast_policy());
+      let result = client.post("", None, &json!({})).await;
+      assert!(result.is_err());
+      assert_eq!(inner.calls(), 1);
+
+      let client = RetryClient::new(inner.clone(), fast_policy().with_retry_post(true));
+      let result = client.post("", None, &json!({})).await;
+      assert!(result.is_ok());
+      assert_eq!(inner.calls(), 3);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io;
+  use std::time::Duration;
+
+  use crate::common::{Headers, HttpError};
+  use crate::retry::{retry_after, RetryPolicy};
+
+  fn fast_policy() -> RetryPolicy {
+    RetryPolicy::new(3, Duration::from_millis(1), Duration::from_millis(10))
+  }
+
+  #[test]
+  fn parses_retry_after() {
+    let mut headers = Headers::new();
+    assert_eq!(retry_after(&headers), None);
+
+    headers.insert("retry-after".to_string(), "120".to_string());
+    assert_eq!(retry_after(&headers), Some(Duration::from_secs(120)));
+
+    headers.insert(
+      "retry-after".to_string(),
+      "Wed, 21 Oct 2015 07:28:00 GMT".to_string(),
+    );
+    assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+  }
+
+  #[test]
+  fn honours_retry_after_up_to_max_delay() {
+    let policy = fast_policy();
+    let mut headers = Headers::new();
+    headers.insert("retry-after".to_string(), "0".to_string());
+    let error = HttpError::<io::Error>::from_status(429, headers.clone(), vec![]);
+    assert_eq!(policy.next_delay(true, 0, &error), Some(Duration::ZERO));
+
+    headers.insert("retry-after".to_string(), "60".to_string());
+    let error = HttpError::<io::Error>::from_status(429, headers, vec![]);
+    assert_eq!(policy.next_delay(true, 0, &error), None);
+  }
+
+  #[test]
+  fn retries_only_transport_errors() {
+    let policy = fast_policy();
+    let refused = io::Error::from(io::ErrorKind::ConnectionRefused);
+    let error = HttpError::<io::Error>::Client(refused);
+    assert!(policy.next_delay(true, 0, &error).is_some());
+    let error = HttpError::<io::Error>::IO(io::ErrorKind::ConnectionReset.into());
+    assert!(policy.next_delay(true, 0, &error).is_some());
+
+    let error = HttpError::<io::Error>::IO(io::ErrorKind::InvalidData.into());
+    assert_eq!(policy.next_delay(true, 0, &error), None);
+    let error = HttpError::Client(std::fmt::Error);
+    assert_eq!(policy.next_delay(true, 0, &error), None);
+  }
+}
@@ -0,0 +1,243 @@
+//! Test doubles shared by the unit tests of the client wrappers.
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::common::{Headers, HttpError, HttpRequest, HttpResponse};
+
+pub(crate) type Reply = Result<u16, u16>;
+
+/// Replies with the scripted statuses in order, `Err` entries become [`HttpError::Status`]
+/// with an `error <status>` body.
+/// Every request is recorded along with the headers it was sent with.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ScriptedClient {
+  replies: Arc<Mutex<VecDeque<Reply>>>,
+  requests: Arc<Mutex<Vec<(String, Headers)>>>,
+  streamed: Arc<AtomicU32>,
+}
+
+impl ScriptedClient {
+  pub fn new(replies: &[Reply]) -> Self {
+    ScriptedClient {
+      replies: Arc::new(Mutex::new(replies.iter().copied().collect())),
+      requests: Default::default(),
+      streamed: Default::default(),
+    }
+  }
+
+  pub fn calls(&self) -> u32 {
+    self.requests.lock().unwrap().len() as u32
+  }
+
+  /// Number of the requests sent with `send_streaming`.
+  pub fn streamed_calls(&self) -> u32 {
+    self.streamed.load(Ordering::SeqCst)
+  }
+
+  pub fn requests(&self) -> Vec<(String, Headers)> {
+    self.requests.lock().unwrap().clone()
+  }
+
+  fn reply(&self, url: &str, headers: &Headers) -> Result<HttpResponse, HttpError<io::Error>> {
+    self
+      .requests
+      .lock()
+      .unwrap()
+      .push((url.to_owned(), headers.clone()));
+
+    match self.replies.lock().unwrap().pop_front().unwrap_or(Ok(200)) {
+      Ok(status) => Ok(HttpResponse::new(status, Headers::new(), String::new())),
+      Err(status) => Err(HttpError::from_status(
+        status,
+        Headers::new(),
+        format!("error {status}").into_bytes(),
+      )),
+    }
+  }
+}
+
+flavoured! {
+  #[maybe_async]
+  impl BaseHttpClient for ScriptedClient {
+    type Error = HttpError<io::Error>;
+
+    async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Self::Error> {
+      self.reply(&request.url, &request.headers)
+    }
+
+    async fn send_streaming(&self, request: &HttpRequest) -> Result<StreamingResponse, Self::Error> {
+      self.streamed.fetch_add(1, Ordering::SeqCst);
+      Ok(self.reply(&request.url, &request.headers)?.into())
+    }
+  }
+}
+
+/// Answers a single request on a local port with the given raw response, on a plain
+/// thread so it works with any async runtime. The handle returns the raw request.
+pub(crate) fn serve_once<R>(response: R) -> (String, std::thread::JoinHandle<String>)
+where
+  R: AsRef<[u8]> + Send + 'static,
+{
+  use std::io::{Read, Write};
+
+  let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+  let url = format!("http://{}", listener.local_addr().unwrap());
+
+  let handle = std::thread::spawn(move || {
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    loop {
+      let read = stream.read(&mut buf).unwrap();
+      request.extend_from_slice(&buf[..read]);
+      let text = String::from_utf8_lossy(&request).to_string();
+      if let Some((head, body)) = text.split_once("\r\n\r\n") {
+        let length = head
+          .lines()
+          .find_map(|line| {
+            let (name, value) = line.split_once(": ")?;
+            name.eq_ignore_ascii_case("content-length").then_some(value)
+          })
+          .map_or(0, |length| length.parse().unwrap());
+        if body.len() >= length {
+          break;
+        }
+      }
+    }
+    stream.write_all(response.as_ref()).unwrap();
+    String::from_utf8(request).unwrap()
+  });
+
+  (url, handle)
+}
+
+/// Local url whose connections are accepted but never answered, for as long as the
+/// listener is kept.
+#[cfg(any(
+  feature = "client-reqwest",
+  feature = "client-ureq",
+  feature = "client-hyper",
+  feature = "client-async-io"
+))]
+pub(crate) fn serve_never() -> (String, std::net::TcpListener) {
+  let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+  let url = format!("http://{}", listener.local_addr().unwrap());
+  (url, listener)
+}
+
+/// Accepts a single connection on a local port, the handle returning the first bytes
+/// received, as long as the HTTP/2 connection preface.
+#[cfg(any(
+  feature = "client-hyper",
+  all(feature = "client-reqwest", feature = "http2")
+))]
+pub(crate) fn accept_preface() -> (String, std::thread::JoinHandle<Vec<u8>>) {
+  use std::io::Read;
+
+  let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+  let url = format!("http://{}", listener.local_addr().unwrap());
+
+  let handle = std::thread::spawn(move || {
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut preface = vec![0; 24];
+    stream.read_exact(&mut preface).unwrap();
+    preface
+  });
+
+  (url, handle)
+}
+
+/// Raw response with the body compressed with `gzip` or `deflate`, for [`serve_once`].
+#[cfg(all(
+  feature = "gzip",
+  feature = "deflate",
+  any(
+    feature = "client-ureq",
+    feature = "client-reqwest",
+    feature = "client-reqwest-blocking"
+  )
+))]
+pub(crate) fn compressed_response(encoding: &str, body: &str) -> Vec<u8> {
+  use std::io::Write;
+
+  use flate2::write::{GzEncoder, ZlibEncoder};
+  use flate2::Compression;
+
+  let compressed = match encoding {
+    "gzip" => {
+      let mut encoder = GzEncoder::new(vec![], Compression::default());
+      encoder.write_all(body.as_bytes()).unwrap();
+      encoder.finish().unwrap()
+    }
+    _ => {
+      let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+      encoder.write_all(body.as_bytes()).unwrap();
+      encoder.finish().unwrap()
+    }
+  };
+  let mut response = format!(
+    "HTTP/1.1 200 OK\r\ncontent-encoding: {encoding}\r\ncontent-length: {}\r\n\r\n",
+    compressed.len()
+  )
+  .into_bytes();
+  response.extend(compressed);
+  response
+}
+
+/// Serves HTTPS with the certificate chain for `connections` connections, answering their
+/// request with an empty `200`. The connections failing the handshake are dropped.
+#[cfg(all(
+  feature = "pinning",
+  any(
+    feature = "client-hyper",
+    all(feature = "client-reqwest", feature = "__reqwest-native-tls")
+  )
+))]
+pub(crate) fn serve_tls(
+  chain: &[&rcgen::Certificate],
+  key: &rcgen::KeyPair,
+  connections: usize,
+) -> (String, std::thread::JoinHandle<()>) {
+  use std::io::{Read, Write};
+
+  use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+  use rustls::{ServerConfig, ServerConnection, StreamOwned};
+
+  let chain = chain.iter().map(|cert| cert.der().clone()).collect();
+  let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key.serialize_der()));
+  let tls = Arc::new(
+    ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+      .with_safe_default_protocol_versions()
+      .unwrap()
+      .with_no_client_auth()
+      .with_single_cert(chain, key)
+      .unwrap(),
+  );
+
+  let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+  let url = format!("https://{}", listener.local_addr().unwrap());
+  let handle = std::thread::spawn(move || {
+    for stream in listener.incoming().take(connections) {
+      let connection = ServerConnection::new(tls.clone()).unwrap();
+      let mut stream = StreamOwned::new(connection, stream.unwrap());
+      let mut request = Vec::new();
+      let mut buffer = [0; 1024];
+      while !request.ends_with(b"\r\n\r\n") {
+        match stream.read(&mut buffer) {
+          Ok(0) | Err(_) => break,
+          Ok(read) => request.extend_from_slice(&buffer[..read]),
+        }
+      }
+      if request.ends_with(b"\r\n\r\n") {
+        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n");
+        stream.conn.send_close_notify();
+        let _ = stream.flush();
+      }
+    }
+  });
+
+  (url, handle)
+}
@@ -0,0 +1,235 @@
+use std::error::Error;
+use std::time::Instant;
+
+use tracing::field::Empty;
+use tracing::Span;
+use url::Url;
+
+use crate::common::{HttpError, HttpRequest, HttpResponse, Method};
+
+/// A `BaseHttpClient` wrapper which runs every request inside a `tracing` span.
+///
+/// The `http.request` span carries the `method`, `host` and `path` of the request, the
+/// `account_id` set with [`TracingClient::with_account`], and once the request is done
+/// its `status` (or `error`) and `duration_ms`.
+#[derive(Debug, Clone, Default)]
+pub struct TracingClient<C> {
+  inner: C,
+  account_id: Option<String>,
+}
+
+impl<C> TracingClient<C> {
+  pub fn new(inner: C) -> Self {
+    TracingClient {
+      inner,
+      account_id: None,
+    }
+  }
+
+  /// Creates a copy of this client which tags its spans with the given account id.
+  pub fn with_account(&self, account_id: impl Into<String>) -> Self
+  where
+    C: Clone,
+  {
+    TracingClient {
+      inner: self.inner.clone(),
+      account_id: Some(account_id.into()),
+    }
+  }
+
+  pub fn inner(&self) -> &C {
+    &self.inner
+  }
+
+  pub fn account_id(&self) -> Option<&str> {
+    self.account_id.as_deref()
+  }
+
+  fn span(&self, method: Method, url: &str) -> Span {
+    let parsed = Url::parse(url).ok();
+    let host = parsed.as_ref().and_then(Url::host_str).unwrap_or_default();
+    // the query and the fragment of the url may hold secrets, keep them out of the spans
+    let path = match &parsed {
+      Some(parsed) => parsed.path(),
+      None => url.split(['?', '#']).next().unwrap_or_default(),
+    };
+
+    tracing::info_span!(
+      "http.request",
+      method = method.as_str(),
+      host,
+      path,
+      account_id = self.account_id.as_deref(),
+      status = Empty,
+      error = Empty,
+      duration_ms = Empty,
+    )
+  }
+}
+
+/// Records the outcome of the request, given the status of the response when it succeeded.
+fn record<E: Error>(span: &Span, result: Result<u16, &HttpError<E>>, started: Instant) {
+  span.record("duration_ms", started.elapsed().as_millis() as u64);
+  match result {
+    Ok(status) => {
+      span.record("status", status);
+    }
+    Err(HttpError::Status { status, .. }) => {
+      span.record("status", status);
+    }
+    Err(error) => {
+      span.record("error", tracing::field::display(error));
+    }
+  }
+}
+
+flavoured! {
+  #[maybe_async]
+  async fn execute<F, Fut, T, E>(
+    span: Span,
+    status: fn(&T) -> u16,
+    send: F,
+  ) -> Result<T, HttpError<E>>
+  where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = Result<T, HttpError<E>>>,
+    E: Error,
+  {
+    let started = Instant::now();
+    let result = in_span(&span, send).await;
+    record(&span, result.as_ref().map(status), started);
+    result
+  }
+
+  #[maybe_async]
+  impl<C, E> BaseHttpClient for TracingClient<C>
+  where
+    C: BaseHttpClient<Error = HttpError<E>> + Sync,
+    E: Error + Send,
+  {
+    type Error = C::Error;
+
+    async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Self::Error> {
+      let span = self.span(request.method, &request.url);
+      execute(span, HttpResponse::status, || self.inner.send(request)).await
+    }
+
+    async fn send_streaming(&self, request: &HttpRequest) -> Result<StreamingResponse, Self::Error> {
+      // the span ends once the headers are received, before the body is read
+      let span = self.span(request.method, &request.url);
+      execute(span, StreamingResponse::status, || {
+        self.inner.send_streaming(request)
+      })
+      .await
+    }
+  }
+
+  #[cfg(test)]
+  mod tests {
+    use std::collections::BTreeMap;
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::Subscriber;
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::Layer;
+
+    use super::*;
+    use crate::common::{HttpRequest, Method, Query};
+    use crate::testing::ScriptedClient;
+    use crate::trace::TracingClient;
+
+    type Fields = Arc<Mutex<BTreeMap<String, String>>>;
+
+    /// Collects the fields of every span into a single map.
+    struct FieldRecorder(Fields);
+
+    impl Visit for FieldRecorder {
+      fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        let value = format!("{value:?}").trim_matches('"').to_string();
+        self
+          .0
+          .lock()
+          .unwrap()
+          .insert(field.name().to_string(), value);
+      }
+    }
+
+    struct RecordingLayer(Fields);
+
+    impl<S: Subscriber> Layer<S> for RecordingLayer {
+      fn on_new_span(&self, attrs: &Attributes<'_>, _: &Id, _: Context<'_, S>) {
+        attrs.record(&mut FieldRecorder(self.0.clone()));
+      }
+
+      fn on_record(&self, _: &Id, values: &Record<'_>, _: Context<'_, S>) {
+        values.record(&mut FieldRecorder(self.0.clone()));
+      }
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn records_request_fields() {
+      let fields = Fields::default();
+      let subscriber = tracing_subscriber::registry().with(RecordingLayer(fields.clone()));
+      let _guard = tracing::subscriber::set_default(subscriber);
+
+      let client = TracingClient::new(ScriptedClient::new(&[Err(503)])).with_account("account-1");
+      let result = client
+        .get(
+          "https://spark.gameforge.com/api/v1/auth/sessions",
+          None,
+          &Query::new(),
+        )
+        .await;
+      assert!(result.is_err());
+
+      let fields = fields.lock().unwrap();
+      assert_eq!(fields["method"], "GET");
+      assert_eq!(fields["host"], "spark.gameforge.com");
+      assert_eq!(fields["path"], "/api/v1/auth/sessions");
+      assert_eq!(fields["account_id"], "account-1");
+      assert_eq!(fields["status"], "503");
+      assert!(fields.contains_key("duration_ms"));
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn records_streaming_requests() {
+      let fields = Fields::default();
+      let subscriber = tracing_subscriber::registry().with(RecordingLayer(fields.clone()));
+      let _guard = tracing::subscriber::set_default(subscriber);
+
+      let inner = ScriptedClient::new(&[Ok(200)]);
+      let client = TracingClient::new(inner.clone());
+      let request = HttpRequest::new(Method::Get, "https://spark.gameforge.com/api/v1");
+      let response = client.send_streaming(&request).await.unwrap();
+      assert_eq!(response.status(), 200);
+      assert_eq!(inner.streamed_calls(), 1);
+
+      let fields = fields.lock().unwrap();
+      assert_eq!(fields["path"], "/api/v1");
+      assert_eq!(fields["status"], "200");
+    }
+
+    #[maybe_async]
+    #[async_test]
+    async fn leaves_the_query_of_unparsed_urls_out() {
+      let fields = Fields::default();
+      let subscriber = tracing_subscriber::registry().with(RecordingLayer(fields.clone()));
+      let _guard = tracing::subscriber::set_default(subscriber);
+
+      let client = TracingClient::new(ScriptedClient::new(&[Ok(200)]));
+      client
+        .get("/api/v1/sessions?token=secret", None, &Query::new())
+        .await
+        .unwrap();
+
+      let fields = fields.lock().unwrap();
+      assert_eq!(fields["host"], "");
+      assert_eq!(fields["path"], "/api/v1/sessions");
+    }
+  }
+}
@@ -1,22 +1,37 @@
 use crate::common::HttpError;
 use crate::ureq::utils::convert_headers;
 use std::error::Error as StdError;
-use ureq::{Error, Response};
+use std::io::{self, Read};
+use ureq::{Error, ErrorKind, Response, Transport};
 
-impl From<Error> for HttpError<Error> {
+impl From<Error> for HttpError<Box<Error>> {
   fn from(error: Error) -> Self {
     match error {
       Error::Status(_, response) => response.into(),
-      Error::Transport(_) => HttpError::Client(error),
+      Error::Transport(ref transport) if is_timeout(transport) => HttpError::Timeout,
+      Error::Transport(_) => HttpError::Client(Box::new(error)),
     }
   }
 }
 
+/// Whether the request reached its timeout, which ureq reports as an I/O error.
+fn is_timeout(transport: &Transport) -> bool {
+  transport.kind() == ErrorKind::Io
+    && transport
+      .source()
+      .and_then(|source| source.downcast_ref::<io::Error>())
+      .is_some_and(|err| err.kind() == io::ErrorKind::TimedOut)
+}
+
 impl<T: StdError> From<Response> for HttpError<T> {
   fn from(response: Response) -> Self {
-    HttpError::Status {
-      status: response.status(),
-      headers: convert_headers(&response),
+    let status = response.status();
+    let headers = convert_headers(&response);
+    // the status is the actual error, so a body that can't be read is left empty
+    let mut body = vec![];
+    if response.into_reader().read_to_end(&mut body).is_err() {
+      body.clear();
     }
+    HttpError::from_status(status, headers, body)
   }
 }
@@ -1,20 +1,159 @@
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::net::{SocketAddr, ToSocketAddrs};
+
+use percent_encoding::percent_decode_str;
+use ureq::{Resolver, Response};
+
 use crate::common::Headers;
-use ureq::Response;
+use crate::config::{ConfigError, Proxy};
 
-pub(crate) fn convert_headers(response: &Response) -> Headers {
-  response
-    .headers_names()
+/// Converts the proxy for ureq, which expects the credentials as they are instead of
+/// percent-encoded and calls `socks5h` just `socks5`.
+pub(crate) fn proxy(proxy: &Proxy) -> Result<ureq::Proxy, ConfigError> {
+  let url = proxy.url();
+  let scheme = match url.scheme() {
+    "http" => "http",
+    "socks5" | "socks5h" => "socks5",
+    _ => return Err(ConfigError::Unsupported("HTTPS proxy")),
+  };
+  let decode = |part: &str| percent_decode_str(part).decode_utf8_lossy().into_owned();
+  let username = decode(url.username());
+  // ureq splits the credentials at the first colon
+  if username.contains(':') {
+    return Err(ConfigError::Proxy("username contains a colon".into()));
+  }
+
+  let mut proxy = format!("{scheme}://");
+  if let Some(password) = url.password() {
+    proxy.push_str(&format!("{username}:{}@", decode(password)));
+  }
+  proxy.push_str(url.host_str().unwrap_or_default());
+  if let Some(port) = url.port_or_known_default() {
+    proxy.push_str(&format!(":{port}"));
+  }
+  ureq::Proxy::new(proxy).map_err(|err| ConfigError::Proxy(err.to_string()))
+}
+
+/// Resolves the overridden host names to their addresses, and the others with the DNS.
+pub(crate) struct OverrideResolver {
+  pub overrides: HashMap<String, Vec<SocketAddr>>,
+}
+
+impl Resolver for OverrideResolver {
+  fn resolve(&self, netloc: &str) -> io::Result<Vec<SocketAddr>> {
+    let addresses = netloc.rsplit_once(':').and_then(|(host, port)| {
+      let addresses = self.overrides.get(host)?;
+      let port = port.parse().ok()?;
+      Some(
+        addresses
+          .iter()
+          .map(|address| match address.port() {
+            0 => SocketAddr::new(address.ip(), port),
+            _ => *address,
+          })
+          .collect(),
+      )
+    });
+    match addresses {
+      Some(addresses) => Ok(addresses),
+      None => netloc.to_socket_addrs().map(Iterator::collect),
+    }
+  }
+}
+
+/// Checks the pinned keys against the certificate of the server once native-tls verified
+/// it, since native-tls has no way to do it during the handshake. The chain it verified
+/// isn't exposed, so only the key of the server itself can be pinned.
+#[cfg(all(feature = "pinning", feature = "ureq-native-tls"))]
+pub(crate) struct PinningConnector {
+  pub inner: native_tls::TlsConnector,
+  pub pins: HashMap<String, Vec<crate::pinning::Pin>>,
+}
+
+#[cfg(all(feature = "pinning", feature = "ureq-native-tls"))]
+impl ureq::TlsConnector for PinningConnector {
+  fn connect(
+    &self,
+    dns_name: &str,
+    io: Box<dyn ureq::ReadWrite>,
+  ) -> Result<Box<dyn ureq::ReadWrite>, ureq::Error> {
+    let stream = self.inner.connect(dns_name, io).map_err(|err| match err {
+      native_tls::HandshakeError::Failure(err) => io::Error::other(err),
+      native_tls::HandshakeError::WouldBlock(_) => {
+        io::Error::new(io::ErrorKind::TimedOut, "native_tls handshake timed out")
+      }
+    })?;
+
+    let certificate = stream
+      .peer_certificate()
+      .and_then(|certificate| {
+        certificate
+          .map(|certificate| certificate.to_der())
+          .transpose()
+      })
+      .map_err(io::Error::other)?;
+    let key = certificate.and_then(|der| crate::pinning::Pin::from_certificate(&der).ok());
+    crate::pinning::verify_pins(&self.pins, dns_name, key)
+      .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Ok(Box::new(stream))
+  }
+}
+
+/// Value of the `Accept-Encoding` header with the encodings the responses are
+/// decompressed from.
+#[cfg(any(feature = "gzip", feature = "deflate", feature = "brotli"))]
+pub(crate) fn accept_encoding() -> String {
+  let encodings = [
+    (cfg!(feature = "gzip"), "gzip"),
+    (cfg!(feature = "deflate"), "deflate"),
+    (cfg!(feature = "brotli"), "br"),
+  ];
+  encodings
     .iter()
-    .filter_map(|key| {
-      let value = match response.header(key) {
-        Some(value) => value.to_string(),
-        None => {
-          log::error!("malformed header received: {key}");
-          return None;
-        }
-      };
-
-      Some((key.to_string().to_lowercase(), value))
-    })
-    .collect()
+    .filter(|(enabled, _)| *enabled)
+    .map(|(_, encoding)| *encoding)
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+/// Splits the response into its status, headers and body, decompressing the `deflate`
+/// encoding which ureq doesn't handle itself.
+pub(crate) fn into_parts(response: Response) -> (u16, Headers, Box<dyn Read + Send>) {
+  let status = response.status();
+  #[allow(unused_mut)]
+  let mut headers = convert_headers(&response);
+
+  #[cfg(feature = "deflate")]
+  if headers
+    .get("content-encoding")
+    .is_some_and(|encoding| encoding.eq_ignore_ascii_case("deflate"))
+  {
+    // the same headers ureq removes from the responses it decompresses
+    headers.remove("content-encoding");
+    headers.remove("content-length");
+    let body = flate2::read::ZlibDecoder::new(response.into_reader());
+    return (status, headers, Box::new(body));
+  }
+
+  (status, headers, response.into_reader())
+}
+
+pub(crate) fn convert_headers(response: &Response) -> Headers {
+  let mut headers = Headers::new();
+  for name in response.headers_names() {
+    // the names of the headers sent more than once are repeated
+    if headers.contains_key(&name) {
+      continue;
+    }
+
+    let values = response.all(&name);
+    if values.is_empty() {
+      log::error!("malformed header received: {name}");
+    }
+    for value in values {
+      headers.append(name.as_str(), value);
+    }
+  }
+  headers
 }
@@ -0,0 +1,200 @@
+//! A local stand-in for the Gameforge Auth APIs, used to test gf-auth end-to-end
+//! without touching the real services.
+//!
+//! The server speaks HTTPS with a freshly generated certificate, whose CA is available
+//! through [`MockServer::ca_pem`]. Client certificates are verified against the
+//! Gameforge "Event Ingest" CA from the `resources` directory by default.
+
+mod routes;
+mod scenario;
+mod tls;
+
+pub use crate::routes::ReceivedRequest;
+pub use crate::scenario::{GameAccount, MockAccount, Scenario, NOSTALE_GAME_ID};
+
+use std::convert::Infallible;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio_rustls::TlsAcceptor;
+
+use crate::routes::{Request, State};
+
+/// The CA that signed the client certificate in the `resources` directory.
+pub const DEFAULT_CLIENT_CA: &[u8] = include_bytes!("../../../resources/ca.pem");
+
+#[derive(thiserror::Error, Debug)]
+pub enum MockServerError {
+  #[error("I/O: {0}")]
+  IO(#[from] io::Error),
+  #[error("certificate: {0}")]
+  Certificate(#[from] rcgen::Error),
+  #[error("TLS: {0}")]
+  Tls(#[from] rustls::Error),
+}
+
+/// A running mock server, which is shut down when dropped.
+#[derive(Debug)]
+pub struct MockServer {
+  addr: SocketAddr,
+  ca_pem: String,
+  state: Arc<Mutex<State>>,
+  shutdown: Option<oneshot::Sender<()>>,
+  task: JoinHandle<()>,
+}
+
+impl MockServer {
+  /// Starts the server on a random local port.
+  pub async fn start(scenario: Scenario) -> Result<Self, MockServerError> {
+    Self::bind(
+      SocketAddr::from(([127, 0, 0, 1], 0)),
+      scenario,
+      DEFAULT_CLIENT_CA,
+    )
+    .await
+  }
+
+  /// Starts the server on the given address, verifying client certificates against
+  /// the PEM encoded `client_ca`.
+  pub async fn bind(
+    addr: SocketAddr,
+    scenario: Scenario,
+    client_ca: &[u8],
+  ) -> Result<Self, MockServerError> {
+    let certificates = tls::generate_certificates()?;
+    let acceptor = TlsAcceptor::from(Arc::new(tls::server_config(&certificates, client_ca)?));
+
+    let listener = TcpListener::bind(addr).await?;
+    let addr = listener.local_addr()?;
+    let state = Arc::new(Mutex::new(State::new(scenario)));
+    let (shutdown, shutdown_rx) = oneshot::channel();
+    let task = tokio::spawn(serve(listener, acceptor, state.clone(), shutdown_rx));
+
+    log::info!("Mock server listening on https://{addr}");
+    Ok(MockServer {
+      addr,
+      ca_pem: certificates.ca_pem,
+      state,
+      shutdown: Some(shutdown),
+      task,
+    })
+  }
+
+  pub fn addr(&self) -> SocketAddr {
+    self.addr
+  }
+
+  /// The base URL of the server, e.g. `https://localhost:51234`.
+  pub fn url(&self) -> String {
+    format!("https://localhost:{}", self.addr.port())
+  }
+
+  /// The PEM encoded CA which signed the server certificate.
+  pub fn ca_pem(&self) -> &str {
+    &self.ca_pem
+  }
+
+  /// All requests the server has received so far.
+  pub fn requests(&self) -> Vec<ReceivedRequest> {
+    self
+      .state
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .requests
+      .clone()
+  }
+
+  /// Stops accepting connections and waits for the server task to finish.
+  pub async fn shutdown(mut self) {
+    if let Some(shutdown) = self.shutdown.take() {
+      let _ = shutdown.send(());
+    }
+    let _ = (&mut self.task).await;
+  }
+}
+
+impl Drop for MockServer {
+  fn drop(&mut self) {
+    self.task.abort();
+  }
+}
+
+async fn serve(
+  listener: TcpListener,
+  acceptor: TlsAcceptor,
+  state: Arc<Mutex<State>>,
+  mut shutdown: oneshot::Receiver<()>,
+) {
+  loop {
+    let stream = tokio::select! {
+      _ = &mut shutdown => return,
+      accepted = listener.accept() => match accepted {
+        Ok((stream, _)) => stream,
+        Err(err) => {
+          log::error!("failed to accept connection: {err}");
+          continue;
+        }
+      },
+    };
+
+    let acceptor = acceptor.clone();
+    let state = state.clone();
+    tokio::spawn(async move {
+      let stream = match acceptor.accept(stream).await {
+        Ok(stream) => stream,
+        Err(err) => {
+          log::debug!("TLS handshake failed: {err}");
+          return;
+        }
+      };
+
+      let client_cert = stream.get_ref().1.peer_certificates().is_some();
+      let service = service_fn(move |request| respond(state.clone(), client_cert, request));
+      if let Err(err) = http1::Builder::new()
+        .serve_connection(TokioIo::new(stream), service)
+        .await
+      {
+        log::debug!("connection error: {err}");
+      }
+    });
+  }
+}
+
+async fn respond(
+  state: Arc<Mutex<State>>,
+  client_cert: bool,
+  request: hyper::Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+  let (parts, body) = request.into_parts();
+  let body = match body.collect().await {
+    Ok(body) => body.to_bytes(),
+    Err(err) => {
+      log::debug!("failed to read request body: {err}");
+      let mut response = Response::new(Full::default());
+      *response.status_mut() = StatusCode::BAD_REQUEST;
+      return Ok(response);
+    }
+  };
+
+  let request = Request {
+    method: parts.method,
+    path: parts.uri.path().to_owned(),
+    headers: parts.headers,
+    body,
+    client_cert,
+  };
+
+  let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+  Ok(routes::handle(&mut state, &request))
+}
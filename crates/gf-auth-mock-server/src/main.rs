@@ -0,0 +1,78 @@
+use std::net::SocketAddr;
+use std::process::ExitCode;
+
+use gf_auth_mock_server::{MockServer, Scenario, DEFAULT_CLIENT_CA};
+
+const USAGE: &str = "\
+Usage: gf-auth-mock-server [OPTIONS]
+
+Options:
+  --addr <ADDR>          Address to listen on [default: 127.0.0.1:8443]
+  --captcha <ANSWER>     Require a captcha, solved by the given answer
+  --otp <CODE>           Require the given one-time password
+  --banned               Reject every login as banned
+  --require-blackbox     Reject logins without a blackbox
+  --require-client-cert  Reject code generation without a client certificate
+  -h, --help             Print this help";
+
+fn parse_args() -> Result<(SocketAddr, Scenario), String> {
+  let mut addr = SocketAddr::from(([127, 0, 0, 1], 8443));
+  let mut scenario = Scenario::default();
+  let mut args = std::env::args().skip(1);
+
+  while let Some(arg) = args.next() {
+    let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+    match arg.as_str() {
+      "--addr" => {
+        addr = value()?
+          .parse()
+          .map_err(|err| format!("invalid address: {err}"))?
+      }
+      "--captcha" => {
+        let answer = value()?
+          .parse()
+          .map_err(|err| format!("invalid captcha answer: {err}"))?;
+        scenario = scenario.with_captcha(answer);
+      }
+      "--otp" => scenario = scenario.with_otp(value()?),
+      "--banned" => scenario = scenario.with_ban(),
+      "--require-blackbox" => scenario = scenario.with_required_blackbox(),
+      "--require-client-cert" => scenario = scenario.with_required_client_cert(),
+      "-h" | "--help" => {
+        println!("{USAGE}");
+        std::process::exit(0);
+      }
+      _ => return Err(format!("unknown argument: {arg}")),
+    }
+  }
+
+  Ok((addr, scenario))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+  let (addr, scenario) = match parse_args() {
+    Ok(args) => args,
+    Err(err) => {
+      eprintln!("{err}\n\n{USAGE}");
+      return ExitCode::FAILURE;
+    }
+  };
+
+  let server = match MockServer::bind(addr, scenario, DEFAULT_CLIENT_CA).await {
+    Ok(server) => server,
+    Err(err) => {
+      eprintln!("failed to start the mock server: {err}");
+      return ExitCode::FAILURE;
+    }
+  };
+
+  println!("Listening on {}", server.url());
+  println!("Server CA certificate:\n{}", server.ca_pem());
+
+  if let Err(err) = tokio::signal::ctrl_c().await {
+    eprintln!("failed to listen for ctrl-c: {err}");
+  }
+  server.shutdown().await;
+  ExitCode::SUCCESS
+}
@@ -0,0 +1,312 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use gf_auth_model::Blackbox;
+use http_body_util::Full;
+use hyper::header::{HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use hyper::{HeaderMap, Method, Response, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::scenario::{MockAccount, Scenario};
+
+/// A transparent 1x1 PNG, served in place of the captcha images.
+const CAPTCHA_IMAGE: &[u8] = &[
+  0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+  0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f, 0x15, 0xc4,
+  0x89, 0x00, 0x00, 0x00, 0x0b, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x60, 0x00, 0x02, 0x00,
+  0x00, 0x05, 0x00, 0x01, 0x7a, 0x5e, 0xab, 0x3f, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44,
+  0xae, 0x42, 0x60, 0x82,
+];
+
+const CHALLENGE_HOST: &str = "https://image-drop-challenge.gameforge.com";
+
+/// A request received by the mock server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedRequest {
+  pub method: String,
+  pub path: String,
+  /// Whether the request was made with a client certificate trusted by the server.
+  pub client_cert: bool,
+}
+
+pub(crate) struct Request {
+  pub method: Method,
+  pub path: String,
+  pub headers: HeaderMap,
+  pub body: Bytes,
+  pub client_cert: bool,
+}
+
+#[derive(Debug)]
+struct Challenge {
+  email: String,
+  solved: bool,
+}
+
+#[derive(Debug)]
+pub(crate) struct State {
+  scenario: Scenario,
+  sessions: HashMap<String, String>,
+  challenges: HashMap<String, Challenge>,
+  solved: HashSet<String>,
+  pub requests: Vec<ReceivedRequest>,
+}
+
+impl State {
+  pub fn new(scenario: Scenario) -> Self {
+    State {
+      scenario,
+      sessions: HashMap::new(),
+      challenges: HashMap::new(),
+      solved: HashSet::new(),
+      requests: vec![],
+    }
+  }
+
+  fn account_for_token(&self, headers: &HeaderMap) -> Option<&MockAccount> {
+    let token = headers
+      .get(AUTHORIZATION)?
+      .to_str()
+      .ok()?
+      .strip_prefix("Bearer ")?;
+    let email = self.sessions.get(token)?;
+
+    self
+      .scenario
+      .accounts
+      .iter()
+      .find(|account| &account.email == email)
+  }
+}
+
+#[derive(Deserialize)]
+struct LoginRequest {
+  email: String,
+  password: String,
+  blackbox: Option<String>,
+  otp: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CodeRequest {
+  platform_game_account_id: Uuid,
+  blackbox: String,
+}
+
+#[derive(Deserialize)]
+struct ChallengeAnswer {
+  answer: u8,
+}
+
+fn json_response(status: StatusCode, body: &Value) -> Response<Full<Bytes>> {
+  let mut response = Response::new(Full::new(Bytes::from(body.to_string())));
+  *response.status_mut() = status;
+  response
+    .headers_mut()
+    .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+  response
+}
+
+fn error(status: StatusCode, message: &str) -> Response<Full<Bytes>> {
+  json_response(status, &json!({ "error": message }))
+}
+
+fn decode_blackbox(blackbox: &str) -> Option<Blackbox> {
+  serde_json::from_value::<Blackbox>(Value::String(blackbox.to_owned())).ok()
+}
+
+fn now_millis() -> u128 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_millis()
+}
+
+pub(crate) fn handle(state: &mut State, request: &Request) -> Response<Full<Bytes>> {
+  log::debug!("{} {}", request.method, request.path);
+  state.requests.push(ReceivedRequest {
+    method: request.method.to_string(),
+    path: request.path.clone(),
+    client_cert: request.client_cert,
+  });
+
+  let segments = request
+    .path
+    .trim_matches('/')
+    .split('/')
+    .collect::<Vec<_>>();
+
+  match (&request.method, segments.as_slice()) {
+    (&Method::POST, ["api", "v1", "auth", "sessions"]) => login(state, request),
+    (&Method::GET, ["api", "v1", "user", "accounts"]) => accounts(state, request),
+    (&Method::POST, ["api", "v1", "auth", "thin", "codes"]) => codes(state, request),
+    (&Method::POST, ["api", "v1", "auth", "iovation"]) => iovation(state, request),
+    (&Method::GET, ["challenge", id, _locale]) => challenge_status(state, id),
+    (&Method::GET, ["challenge", id, _locale, "text" | "drag-icons" | "drop-target"]) => {
+      challenge_image(state, id)
+    }
+    (&Method::POST, ["challenge", id, _locale]) => challenge_answer(state, id, request),
+    _ => error(StatusCode::NOT_FOUND, "not found"),
+  }
+}
+
+fn login(state: &mut State, request: &Request) -> Response<Full<Bytes>> {
+  let Ok(body) = serde_json::from_slice::<LoginRequest>(&request.body) else {
+    return error(StatusCode::BAD_REQUEST, "malformed request");
+  };
+
+  match &body.blackbox {
+    Some(blackbox) if decode_blackbox(blackbox).is_none() => {
+      return error(StatusCode::BAD_REQUEST, "invalid blackbox");
+    }
+    None if state.scenario.require_blackbox => {
+      return error(StatusCode::BAD_REQUEST, "missing blackbox");
+    }
+    _ => {}
+  }
+
+  let valid = state
+    .scenario
+    .accounts
+    .iter()
+    .any(|account| account.email == body.email && account.password == body.password);
+  if !valid {
+    return error(StatusCode::FORBIDDEN, "invalid credentials");
+  }
+
+  if state.scenario.banned {
+    return error(StatusCode::FORBIDDEN, "account banned");
+  }
+
+  if state.scenario.captcha && !state.solved.contains(&body.email) {
+    let id = Uuid::new_v4().to_string();
+    let mut response = error(StatusCode::CONFLICT, "captcha required");
+    response.headers_mut().insert(
+      "gf-challenge-id",
+      HeaderValue::from_str(&format!("{id};{CHALLENGE_HOST}")).expect("valid header value"),
+    );
+    state.challenges.insert(
+      id,
+      Challenge {
+        email: body.email,
+        solved: false,
+      },
+    );
+    return response;
+  }
+
+  if state.scenario.otp.is_some() && state.scenario.otp != body.otp {
+    return json_response(StatusCode::CONFLICT, &json!({ "otpRequired": true }));
+  }
+
+  let token = Uuid::new_v4().to_string();
+  state.sessions.insert(token.clone(), body.email);
+  json_response(StatusCode::CREATED, &json!({ "token": token }))
+}
+
+fn accounts(state: &State, request: &Request) -> Response<Full<Bytes>> {
+  let Some(account) = state.account_for_token(&request.headers) else {
+    return error(StatusCode::UNAUTHORIZED, "invalid token");
+  };
+
+  let accounts = account
+    .game_accounts
+    .iter()
+    .map(|game_account| (game_account.id.to_string(), json!(game_account)))
+    .collect::<serde_json::Map<_, _>>();
+
+  json_response(StatusCode::OK, &Value::Object(accounts))
+}
+
+fn codes(state: &State, request: &Request) -> Response<Full<Bytes>> {
+  if state.scenario.require_client_cert && !request.client_cert {
+    return error(StatusCode::FORBIDDEN, "client certificate required");
+  }
+
+  let Some(account) = state.account_for_token(&request.headers) else {
+    return error(StatusCode::UNAUTHORIZED, "invalid token");
+  };
+
+  let Ok(body) = serde_json::from_slice::<CodeRequest>(&request.body) else {
+    return error(StatusCode::BAD_REQUEST, "malformed request");
+  };
+
+  if decode_blackbox(&body.blackbox).is_none() {
+    return error(StatusCode::BAD_REQUEST, "invalid blackbox");
+  }
+
+  let owned = account
+    .game_accounts
+    .iter()
+    .any(|game_account| game_account.id == body.platform_game_account_id);
+  if !owned {
+    return error(StatusCode::FORBIDDEN, "unknown game account");
+  }
+
+  let code = Uuid::new_v4().simple().to_string();
+  json_response(StatusCode::CREATED, &json!({ "code": code }))
+}
+
+fn iovation(state: &State, request: &Request) -> Response<Full<Bytes>> {
+  if state.account_for_token(&request.headers).is_none() {
+    return error(StatusCode::UNAUTHORIZED, "invalid token");
+  }
+
+  let blackbox = serde_json::from_slice::<Value>(&request.body)
+    .ok()
+    .and_then(|body| body.get("blackbox")?.as_str().map(str::to_owned));
+  match blackbox {
+    Some(blackbox) if decode_blackbox(&blackbox).is_some() => {
+      json_response(StatusCode::OK, &json!({ "status": "ok" }))
+    }
+    _ => error(StatusCode::BAD_REQUEST, "invalid blackbox"),
+  }
+}
+
+fn challenge_status(state: &State, id: &str) -> Response<Full<Bytes>> {
+  match state.challenges.get(id) {
+    Some(challenge) => json_response(
+      StatusCode::OK,
+      &json!({
+        "id": id,
+        "status": if challenge.solved { "solved" } else { "presented" },
+        "lastUpdated": now_millis(),
+      }),
+    ),
+    None => error(StatusCode::NOT_FOUND, "unknown challenge"),
+  }
+}
+
+fn challenge_image(state: &State, id: &str) -> Response<Full<Bytes>> {
+  if !state.challenges.contains_key(id) {
+    return error(StatusCode::NOT_FOUND, "unknown challenge");
+  }
+
+  let mut response = Response::new(Full::new(Bytes::from_static(CAPTCHA_IMAGE)));
+  response
+    .headers_mut()
+    .insert(CONTENT_TYPE, HeaderValue::from_static("image/png"));
+  response
+}
+
+fn challenge_answer(state: &mut State, id: &str, request: &Request) -> Response<Full<Bytes>> {
+  let Ok(body) = serde_json::from_slice::<ChallengeAnswer>(&request.body) else {
+    return error(StatusCode::BAD_REQUEST, "malformed request");
+  };
+
+  let answer = state.scenario.captcha_answer;
+  let Some(challenge) = state.challenges.get_mut(id) else {
+    return error(StatusCode::NOT_FOUND, "unknown challenge");
+  };
+
+  if body.answer == answer {
+    challenge.solved = true;
+    state.solved.insert(challenge.email.clone());
+  }
+
+  challenge_status(state, id)
+}
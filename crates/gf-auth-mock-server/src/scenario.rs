@@ -0,0 +1,120 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+/// The NosTale game id used by the Gameforge APIs.
+pub const NOSTALE_GAME_ID: &str = "dd4e22d6-00d1-44b9-8126-d8b40e0cd7c9";
+
+/// A game account owned by a [`MockAccount`], as returned by the accounts endpoint.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GameAccount {
+  pub id: Uuid,
+  pub account_group: String,
+  pub display_name: String,
+  pub game_id: String,
+}
+
+impl GameAccount {
+  pub fn new(display_name: impl Into<String>) -> Self {
+    GameAccount {
+      id: Uuid::new_v4(),
+      account_group: "en_GB".to_string(),
+      display_name: display_name.into(),
+      game_id: NOSTALE_GAME_ID.to_string(),
+    }
+  }
+}
+
+/// A Gameforge account the mock server accepts logins for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockAccount {
+  pub email: String,
+  pub password: String,
+  pub game_accounts: Vec<GameAccount>,
+}
+
+impl MockAccount {
+  pub fn new(email: impl Into<String>, password: impl Into<String>) -> Self {
+    MockAccount {
+      email: email.into(),
+      password: password.into(),
+      game_accounts: vec![],
+    }
+  }
+
+  pub fn with_game_account(mut self, account: GameAccount) -> Self {
+    self.game_accounts.push(account);
+    self
+  }
+}
+
+impl Default for MockAccount {
+  fn default() -> Self {
+    MockAccount::new("user@example.com", "password").with_game_account(GameAccount::new("user"))
+  }
+}
+
+/// Describes how the mock server behaves.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+  pub accounts: Vec<MockAccount>,
+  /// Require a solved captcha before a login succeeds.
+  pub captcha: bool,
+  /// The answer which solves the captcha.
+  pub captcha_answer: u8,
+  /// Require this one-time password in the `otp` field of the login request.
+  pub otp: Option<String>,
+  /// Reject every login as if the account was banned.
+  pub banned: bool,
+  /// Reject logins which don't carry a blackbox.
+  pub require_blackbox: bool,
+  /// Reject code generation requests made without a valid client certificate.
+  pub require_client_cert: bool,
+}
+
+impl Default for Scenario {
+  fn default() -> Self {
+    Scenario {
+      accounts: vec![MockAccount::default()],
+      captcha: false,
+      captcha_answer: 0,
+      otp: None,
+      banned: false,
+      require_blackbox: false,
+      require_client_cert: false,
+    }
+  }
+}
+
+impl Scenario {
+  pub fn with_account(mut self, account: MockAccount) -> Self {
+    self.accounts.push(account);
+    self
+  }
+
+  pub fn with_captcha(mut self, answer: u8) -> Self {
+    self.captcha = true;
+    self.captcha_answer = answer;
+    self
+  }
+
+  pub fn with_otp(mut self, otp: impl Into<String>) -> Self {
+    self.otp = Some(otp.into());
+    self
+  }
+
+  pub fn with_ban(mut self) -> Self {
+    self.banned = true;
+    self
+  }
+
+  pub fn with_required_blackbox(mut self) -> Self {
+    self.require_blackbox = true;
+    self
+  }
+
+  pub fn with_required_client_cert(mut self) -> Self {
+    self.require_client_cert = true;
+    self
+  }
+}
@@ -0,0 +1,81 @@
+use std::io::Cursor;
+use std::sync::Arc;
+
+use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair, KeyUsagePurpose};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+
+use crate::MockServerError;
+
+/// Host names the generated server certificate is valid for, so that clients which
+/// resolve the Gameforge hosts to the mock server can still validate it.
+pub(crate) const CERTIFICATE_HOSTS: &[&str] = &[
+  "localhost",
+  "127.0.0.1",
+  "::1",
+  "spark.gameforge.com",
+  "image-drop-challenge.gameforge.com",
+  "events2.gameforge.com",
+];
+
+pub(crate) struct ServerCertificates {
+  pub ca_pem: String,
+  pub chain: Vec<CertificateDer<'static>>,
+  pub key: PrivateKeyDer<'static>,
+}
+
+/// Generates a throw-away CA and a server certificate signed by it.
+pub(crate) fn generate_certificates() -> Result<ServerCertificates, MockServerError> {
+  let ca_key = KeyPair::generate()?;
+  let mut ca_params = CertificateParams::new(Vec::<String>::new())?;
+  ca_params
+    .distinguished_name
+    .push(DnType::CommonName, "gf-auth mock server CA");
+  ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+  ca_params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+  let ca = ca_params.self_signed(&ca_key)?;
+
+  let server_key = KeyPair::generate()?;
+  let hosts = CERTIFICATE_HOSTS
+    .iter()
+    .map(|host| host.to_string())
+    .collect::<Vec<_>>();
+  let mut server_params = CertificateParams::new(hosts)?;
+  server_params
+    .distinguished_name
+    .push(DnType::CommonName, "gf-auth mock server");
+  let server = server_params.signed_by(&server_key, &ca, &ca_key)?;
+
+  Ok(ServerCertificates {
+    ca_pem: ca.pem(),
+    chain: vec![server.der().clone(), ca.der().clone()],
+    key: PrivatePkcs8KeyDer::from(server_key.serialize_der()).into(),
+  })
+}
+
+/// Builds the TLS configuration, client certificates are verified against `client_ca`
+/// when presented, but are not required on the TLS level.
+pub(crate) fn server_config(
+  certificates: &ServerCertificates,
+  client_ca: &[u8],
+) -> Result<ServerConfig, MockServerError> {
+  let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+  let mut roots = RootCertStore::empty();
+  for certificate in rustls_pemfile::certs(&mut Cursor::new(client_ca)) {
+    roots.add(certificate?)?;
+  }
+
+  let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+    .allow_unauthenticated()
+    .build()
+    .map_err(|err| MockServerError::Tls(rustls::Error::General(err.to_string())))?;
+
+  let config = ServerConfig::builder_with_provider(provider)
+    .with_safe_default_protocol_versions()?
+    .with_client_cert_verifier(verifier)
+    .with_single_cert(certificates.chain.clone(), certificates.key.clone_key())?;
+
+  Ok(config)
+}
@@ -0,0 +1,144 @@
+use gf_auth_http::{BaseHttpClient, CustomCertHttpClient, Headers, HttpClient, HttpError, Query};
+use gf_auth_mock_server::{MockServer, Scenario};
+use serde_json::{json, Value};
+
+const BLACKBOX: &str = include_str!("../../../resources/blackbox/encoded_blackbox.txt");
+const CLIENT_CERT: &[u8] = include_bytes!("../../../resources/client.pem");
+const CLIENT_KEY: &[u8] = include_bytes!("../../../resources/key.pem");
+
+fn client(server: &MockServer) -> HttpClient {
+  HttpClient::with_custom_cert(&server.ca_pem(), &CLIENT_CERT, &CLIENT_KEY)
+}
+
+fn bearer(token: &str) -> Headers {
+  Headers::from([("Authorization".to_string(), format!("Bearer {token}"))])
+}
+
+async fn login(client: &HttpClient, server: &MockServer, payload: Value) -> Result<Value, u16> {
+  let url = format!("{}/api/v1/auth/sessions", server.url());
+  match client.post(&url, None, &payload).await {
+    Ok(response) => Ok(response.json().unwrap()),
+    Err(HttpError::Status { status, .. }) => Err(status),
+    Err(err) => panic!("request failed: {err}"),
+  }
+}
+
+fn credentials() -> Value {
+  json!({
+    "email": "user@example.com",
+    "password": "password",
+    "blackbox": BLACKBOX.trim(),
+  })
+}
+
+#[tokio::test]
+async fn login_lists_accounts_and_generates_code() {
+  let server = MockServer::start(Scenario::default()).await.unwrap();
+  let client = client(&server);
+
+  let session = login(&client, &server, credentials()).await.unwrap();
+  let headers = bearer(session["token"].as_str().unwrap());
+
+  let accounts = client
+    .get(
+      &format!("{}/api/v1/user/accounts", server.url()),
+      Some(&headers),
+      &Query::new(),
+    )
+    .await
+    .unwrap();
+  let accounts = accounts.json::<Value>().unwrap();
+  let (id, account) = accounts.as_object().unwrap().iter().next().unwrap();
+  assert_eq!(account["displayName"], "user");
+
+  let code = client
+    .post(
+      &format!("{}/api/v1/auth/thin/codes", server.url()),
+      Some(&headers),
+      &json!({ "platformGameAccountId": id, "blackbox": BLACKBOX.trim() }),
+    )
+    .await
+    .unwrap();
+  assert_eq!(code.status(), 201);
+
+  let requests = server.requests();
+  assert_eq!(requests.len(), 3);
+  assert!(requests.iter().all(|request| request.client_cert));
+}
+
+#[tokio::test]
+async fn rejects_invalid_credentials_and_blackbox() {
+  let server = MockServer::start(Scenario::default().with_required_blackbox())
+    .await
+    .unwrap();
+  let client = client(&server);
+
+  let mut wrong_password = credentials();
+  wrong_password["password"] = json!("wrong");
+  let missing_blackbox = json!({ "email": "user@example.com", "password": "password" });
+  let invalid_blackbox = json!({
+    "email": "user@example.com",
+    "password": "password",
+    "blackbox": "tra:invalid",
+  });
+
+  assert_eq!(login(&client, &server, wrong_password).await, Err(403));
+  assert_eq!(login(&client, &server, missing_blackbox).await, Err(400));
+  assert_eq!(login(&client, &server, invalid_blackbox).await, Err(400));
+}
+
+#[tokio::test]
+async fn captcha_must_be_solved_before_login() {
+  let server = MockServer::start(Scenario::default().with_captcha(3))
+    .await
+    .unwrap();
+  let client = client(&server);
+  let url = format!("{}/api/v1/auth/sessions", server.url());
+
+  let challenge_id = match client.post(&url, None, &credentials()).await {
+    Err(HttpError::Status {
+      status, headers, ..
+    }) => {
+      assert_eq!(status, 409);
+      let header = headers.get("gf-challenge-id").unwrap();
+      header.split(';').next().unwrap().to_string()
+    }
+    _ => panic!("expected a captcha challenge"),
+  };
+
+  let challenge_url = format!("{}/challenge/{challenge_id}/en-GB", server.url());
+  let wrong = client
+    .post(&challenge_url, None, &json!({ "answer": 1 }))
+    .await
+    .unwrap();
+  let wrong = wrong.json::<Value>().unwrap();
+  assert_eq!(wrong["status"], "presented");
+
+  let solved = client
+    .post(&challenge_url, None, &json!({ "answer": 3 }))
+    .await
+    .unwrap();
+  let solved = solved.json::<Value>().unwrap();
+  assert_eq!(solved["status"], "solved");
+
+  assert!(login(&client, &server, credentials()).await.is_ok());
+}
+
+#[tokio::test]
+async fn otp_and_ban_scenarios() {
+  let server = MockServer::start(Scenario::default().with_otp("123456"))
+    .await
+    .unwrap();
+  let client = client(&server);
+
+  assert_eq!(login(&client, &server, credentials()).await, Err(409));
+  let mut with_otp = credentials();
+  with_otp["otp"] = json!("123456");
+  assert!(login(&client, &server, with_otp).await.is_ok());
+
+  let banned = MockServer::start(Scenario::default().with_ban())
+    .await
+    .unwrap();
+  let client = self::client(&banned);
+  assert_eq!(login(&client, &banned, credentials()).await, Err(403));
+}
@@ -80,6 +80,15 @@ pub struct Fingerprint {
 }
 
 impl Fingerprint {
+  pub fn user_agent(&self) -> &str {
+    &self.user_agent
+  }
+
+  /// The preferred languages of the browser, separated with commas.
+  pub fn languages(&self) -> &str {
+    &self.languages
+  }
+
   pub fn update_vector(&mut self) {
     self.vector.update();
   }
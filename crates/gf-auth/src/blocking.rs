@@ -0,0 +1,11 @@
+//! The synchronous flavour of the gf-auth items built on the HTTP clients.
+
+/// The flavour specific items used by the code compiled with `flavoured!`, which only
+/// exists with some of the optional features.
+#[allow(unused_imports)]
+pub(crate) mod prelude {
+  #[cfg(test)]
+  pub(crate) use core::prelude::v1::test as async_test;
+  pub(crate) use gf_auth_http::blocking::BaseHttpClient;
+  pub(crate) use maybe_async::must_be_sync as maybe_async;
+}
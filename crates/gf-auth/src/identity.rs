@@ -0,0 +1,64 @@
+use gf_auth_http::CookieJar;
+use gf_auth_model::Identity;
+use serde::{Deserialize, Serialize};
+
+/// An [`Identity`] stored together with the cookies of its session, so loading it again
+/// resumes the same session.
+///
+/// It's serialized as the identity itself with an additional `cookies` field, so plain
+/// identity files can be loaded as well.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PersistedIdentity {
+  #[serde(flatten)]
+  pub identity: Identity,
+  #[serde(default)]
+  pub cookies: CookieJar,
+}
+
+impl PersistedIdentity {
+  pub fn new(identity: Identity) -> Self {
+    Self {
+      identity,
+      cookies: CookieJar::new(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::identity::PersistedIdentity;
+  use std::fs;
+
+  const URL: &str = "https://spark.gameforge.com/api/v1/auth/sessions";
+
+  #[test]
+  fn loads_identity_without_cookies() {
+    let file = fs::read_to_string("../../resources/identity/identity_full.json")
+      .expect("Failed to read identity file.");
+
+    let persisted = serde_json::from_str::<PersistedIdentity>(&file).unwrap();
+    assert!(persisted.cookies.is_empty());
+  }
+
+  #[test]
+  fn persists_cookies_alongside_identity() {
+    let file = fs::read_to_string("../../resources/identity/identity_full.json")
+      .expect("Failed to read identity file.");
+    let persisted = serde_json::from_str::<PersistedIdentity>(&file).unwrap();
+    persisted.cookies.insert(URL, "gf-challenge=abc; Path=/");
+
+    let json = serde_json::to_value(&persisted).unwrap();
+    assert!(json.get("fingerprint").is_some());
+    assert!(json.get("cookies").is_some());
+
+    let restored = serde_json::from_value::<PersistedIdentity>(json).unwrap();
+    assert_eq!(
+      restored.identity.installation_id,
+      persisted.identity.installation_id
+    );
+    assert_eq!(
+      restored.cookies.get(URL, "gf-challenge"),
+      Some("abc".to_string())
+    );
+  }
+}
@@ -0,0 +1,102 @@
+use gf_auth_http::{LauncherProfile, MiddlewareClient};
+use gf_auth_model::Identity;
+
+/// Version of the Gameforge launcher the requests are made as by default.
+pub const LAUNCHER_VERSION: &str = "2.2.23.1813";
+
+/// Derives the headers of the launcher from an [`Identity`], keeping them consistent with
+/// its fingerprint.
+///
+/// The user agent is the one of the fingerprint followed by the launcher version, and
+/// the installation id is the one of the identity. The `Accept-Language` header follows
+/// the languages of the fingerprint unless a locale is set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LauncherHeaders {
+  pub version: String,
+  pub locale: Option<String>,
+}
+
+impl Default for LauncherHeaders {
+  fn default() -> Self {
+    LauncherHeaders {
+      version: LAUNCHER_VERSION.to_string(),
+      locale: None,
+    }
+  }
+}
+
+impl LauncherHeaders {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn with_version(mut self, version: impl Into<String>) -> Self {
+    self.version = version.into();
+    self
+  }
+
+  pub fn with_locale(mut self, locale: impl Into<String>) -> Self {
+    self.locale = Some(locale.into());
+    self
+  }
+
+  pub fn profile(&self, identity: &Identity) -> LauncherProfile {
+    let mut profile = LauncherProfile::from_identity(identity);
+    profile.user_agent = format!("{} GameforgeClient/{}", profile.user_agent, self.version);
+    if let Some(locale) = &self.locale {
+      profile.accept_language.clone_from(locale);
+    }
+    profile
+  }
+
+  /// Wraps the client used to talk to Gameforge, so every request it makes carries the
+  /// headers of the identity.
+  pub fn client<C>(&self, client: C, identity: &Identity) -> MiddlewareClient<C> {
+    MiddlewareClient::new(client).with(self.profile(identity))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use gf_auth_model::Identity;
+
+  use crate::launcher::LauncherHeaders;
+
+  fn identity() -> Identity {
+    let file = fs::read_to_string("../../resources/identity/identity_full.json")
+      .expect("Failed to read identity file.");
+    serde_json::from_str(&file).unwrap()
+  }
+
+  #[test]
+  fn derives_headers_from_identity() {
+    let identity = identity();
+    let headers = LauncherHeaders::new()
+      .with_version("2.3.0.1900")
+      .profile(&identity)
+      .headers();
+
+    assert_eq!(
+      headers.get("user-agent"),
+      Some(
+        "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) \
+        Chrome/125.0.0.0 Safari/537.36 GameforgeClient/2.3.0.1900"
+      )
+    );
+    assert_eq!(
+      headers.get("tnt-installation-id"),
+      Some("fe2495d2-0d0c-4dde-8525-076ff5570a59")
+    );
+    assert_eq!(
+      headers.get("accept-language"),
+      Some("en-US,pl;q=0.9,en;q=0.8")
+    );
+
+    let profile = LauncherHeaders::new()
+      .with_locale("de-DE")
+      .profile(&identity);
+    assert_eq!(profile.accept_language, "de-DE");
+  }
+}
@@ -1,2 +1,41 @@
+/// Compiles the given items once for every enabled flavour, in the `nonblocking` and
+/// `blocking` submodules of the current module, like the `flavoured!` macro of gf-auth-http.
+#[allow(unused_macros)]
+macro_rules! flavoured {
+  ($($item:tt)*) => {
+    #[cfg(feature = "__async")]
+    pub(crate) mod nonblocking {
+      #[allow(unused_imports)]
+      use super::*;
+      #[allow(unused_imports)]
+      use crate::nonblocking::prelude::*;
+
+      $($item)*
+    }
+
+    #[cfg(feature = "__sync")]
+    pub(crate) mod blocking {
+      #[allow(unused_imports)]
+      use super::*;
+      #[allow(unused_imports)]
+      use crate::blocking::prelude::*;
+
+      $($item)*
+    }
+  };
+}
+
+#[cfg(feature = "__sync")]
+mod blocking;
 mod identity;
+mod launcher;
+#[cfg(feature = "metrics")]
+mod metric;
+#[cfg(feature = "__async")]
+mod nonblocking;
 mod sync;
+
+pub use crate::identity::PersistedIdentity;
+pub use crate::launcher::{LauncherHeaders, LAUNCHER_VERSION};
+#[cfg(feature = "metrics")]
+pub use crate::metric::{AuthMetrics, CAPTCHAS_TOTAL, CODES_GENERATED_TOTAL, LOGINS_TOTAL};
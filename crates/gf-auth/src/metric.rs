@@ -0,0 +1,134 @@
+use std::error::Error;
+
+use gf_auth_http::{HttpError, HttpRequest, HttpResponse, Method};
+use metrics::{counter, describe_counter, Unit};
+use url::Url;
+
+/// Counter of the login attempts, labelled with their `outcome`: `success`, `captcha` when
+/// a captcha has to be solved first, or `failure`.
+pub const LOGINS_TOTAL: &str = "gf_auth_logins_total";
+/// Counter of the captchas Gameforge asked to solve.
+pub const CAPTCHAS_TOTAL: &str = "gf_auth_captchas_total";
+/// Counter of the game codes generated.
+pub const CODES_GENERATED_TOTAL: &str = "gf_auth_codes_generated_total";
+
+/// A `BaseHttpClient` wrapper counting the logins, captchas and codes of the auth calls made
+/// through it, with the `metrics` facade.
+///
+/// The per endpoint request metrics come from
+/// [`MetricsClient`](gf_auth_http::MetricsClient), which can wrap the same client.
+#[derive(Debug, Clone, Default)]
+pub struct AuthMetrics<C> {
+  inner: C,
+}
+
+impl<C> AuthMetrics<C> {
+  pub fn new(inner: C) -> Self {
+    AuthMetrics { inner }
+  }
+
+  pub fn inner(&self) -> &C {
+    &self.inner
+  }
+
+  /// Registers the units and descriptions of the metrics with the installed recorder.
+  pub fn describe() {
+    describe_counter!(LOGINS_TOTAL, Unit::Count, "Login attempts");
+    describe_counter!(CAPTCHAS_TOTAL, Unit::Count, "Captchas encountered");
+    describe_counter!(CODES_GENERATED_TOTAL, Unit::Count, "Game codes generated");
+  }
+}
+
+fn record<E: Error>(request: &HttpRequest, result: &Result<HttpResponse, HttpError<E>>) {
+  let Ok(url) = Url::parse(&request.url) else {
+    return;
+  };
+  if request.method != Method::Post {
+    return;
+  }
+
+  match url.path().trim_end_matches('/') {
+    path if path.ends_with("/auth/sessions") => {
+      let outcome = match result {
+        Ok(_) => "success",
+        // the login is rejected until the challenge of the header is solved
+        Err(HttpError::Status {
+          status: 409,
+          headers,
+          ..
+        }) if headers.contains_key("gf-challenge-id") => {
+          counter!(CAPTCHAS_TOTAL).increment(1);
+          "captcha"
+        }
+        Err(_) => "failure",
+      };
+      counter!(LOGINS_TOTAL, "outcome" => outcome).increment(1);
+    }
+    path if path.ends_with("/auth/thin/codes") && result.is_ok() => {
+      counter!(CODES_GENERATED_TOTAL).increment(1);
+    }
+    _ => {}
+  }
+}
+
+flavoured! {
+  #[maybe_async]
+  impl<C, E> BaseHttpClient for AuthMetrics<C>
+  where
+    C: BaseHttpClient<Error = HttpError<E>> + Sync,
+    E: Error + Send,
+  {
+    type Error = C::Error;
+
+    async fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Self::Error> {
+      let result = self.inner.send(request).await;
+      record(request, &result);
+      result
+    }
+  }
+
+  #[cfg(test)]
+  mod tests {
+    use super::*;
+    use gf_auth_http::{Expectation, Method, MetricsRecorder, MockHttpClient};
+    use serde_json::json;
+
+    use crate::metric::AuthMetrics;
+
+    const SESSIONS_URL: &str = "https://spark.gameforge.com/api/v1/auth/sessions";
+    const CODES_URL: &str = "https://spark.gameforge.com/api/v1/auth/thin/codes";
+
+    #[maybe_async]
+    #[async_test]
+    async fn counts_auth_outcomes() {
+      let recorder = MetricsRecorder::default();
+      let _guard = metrics::set_default_local_recorder(&recorder);
+
+      let mock = MockHttpClient::new();
+      mock.expect(
+        Expectation::new(Method::Post, SESSIONS_URL)
+          .respond_with(409, "")
+          .respond_with_header("gf-challenge-id", "challenge")
+          .times(1),
+      );
+      mock.expect(Expectation::new(Method::Post, SESSIONS_URL).respond_with_json(201, &json!({})));
+      mock.expect(Expectation::new(Method::Post, CODES_URL).respond_with_json(201, &json!({})));
+      let client = AuthMetrics::new(mock);
+
+      let captcha = client.post(SESSIONS_URL, None, &json!({})).await;
+      assert!(captcha.is_err());
+      client.post(SESSIONS_URL, None, &json!({})).await.unwrap();
+      client.post(CODES_URL, None, &json!({})).await.unwrap();
+
+      assert_eq!(
+        recorder.counters(),
+        [
+          "gf_auth_captchas_total{} 1",
+          "gf_auth_codes_generated_total{} 1",
+          "gf_auth_logins_total{outcome=captcha} 1",
+          "gf_auth_logins_total{outcome=success} 1",
+        ]
+      );
+    }
+  }
+}
@@ -0,0 +1,11 @@
+//! The asynchronous flavour of the gf-auth items built on the HTTP clients.
+
+/// The flavour specific items used by the code compiled with `flavoured!`, which only
+/// exists with some of the optional features.
+#[allow(unused_imports)]
+pub(crate) mod prelude {
+  pub(crate) use gf_auth_http::nonblocking::BaseHttpClient;
+  pub(crate) use maybe_async::must_be_async as maybe_async;
+  #[cfg(test)]
+  pub(crate) use tokio::test as async_test;
+}
@@ -1 +1,2 @@
+#[allow(unused_imports)]
 pub use std::sync::Mutex;
@@ -1,16 +1,7 @@
-//! Synchronization primitives that have both synchronous and asynchronous variants under the same
-//! interface.
+//! Synchronization primitives with a synchronous and an asynchronous variant under the same
+//! interface, in the `blocking` and `nonblocking` flavours.
 
 #[cfg(feature = "__sync")]
-mod blocking;
+pub(crate) mod blocking;
 #[cfg(feature = "__async")]
-mod futures;
-
-#[cfg(feature = "__sync")]
-use self::blocking as imp;
-#[cfg(feature = "__async")]
-use self::futures as imp;
-
-/// A type alias for either an asynchronous mutex or [`std::sync::Mutex`], depending on whether
-/// this library is compiled in asynchronous or synchronous mode.
-pub type Mutex<T> = imp::Mutex<T>;
+pub(crate) mod nonblocking;
@@ -1,3 +1,4 @@
+/// Asynchronous mutex backed by `futures`, so it works on any async runtime, not only tokio.
 #[derive(Debug, Default)]
 pub struct Mutex<T: ?Sized>(futures::lock::Mutex<T>);
 
@@ -6,6 +7,7 @@ pub struct Mutex<T: ?Sized>(futures::lock::Mutex<T>);
 pub struct LockError;
 
 impl<T> Mutex<T> {
+  #[allow(dead_code)]
   pub fn new(val: T) -> Self {
     Self(futures::lock::Mutex::new(val))
   }